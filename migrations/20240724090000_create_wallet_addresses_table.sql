@@ -0,0 +1,12 @@
+-- Wallet-backed users sign in without an email address.
+ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+
+CREATE TABLE wallet_addresses (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    address VARCHAR(42) NOT NULL UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_wallet_addresses_user_id ON wallet_addresses (user_id);
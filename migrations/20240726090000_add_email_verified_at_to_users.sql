@@ -0,0 +1,5 @@
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE;
+
+-- Accounts that predate verification are trusted as they are, so turning on
+-- REQUIRE_EMAIL_VERIFICATION does not lock their owners out.
+UPDATE users SET email_verified_at = COALESCE(created_at, CURRENT_TIMESTAMP);
@@ -0,0 +1,25 @@
+-- RFC 8628 device authorization requests from input-constrained clients.
+CREATE TABLE oauth2_device_codes (
+    id SERIAL PRIMARY KEY,
+    device_code_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- Normalized code the user types in on another device.
+    user_code VARCHAR(16) NOT NULL,
+    client_id VARCHAR(64) NOT NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    -- 'pending', 'approved' or 'denied'.
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    -- Set once the user approves or denies the request.
+    user_id INTEGER,
+    -- Minimum number of seconds between polls; grows when the client polls too often.
+    interval_seconds INTEGER NOT NULL,
+    last_polled_at TIMESTAMP WITH TIME ZONE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (client_id) REFERENCES oauth2_clients (client_id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+-- A user code must identify a single request while the user can still enter it.
+CREATE UNIQUE INDEX idx_oauth2_device_codes_pending_user_code ON oauth2_device_codes (user_code)
+WHERE status = 'pending';
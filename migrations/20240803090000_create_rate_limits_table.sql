@@ -0,0 +1,7 @@
+-- Request counters for endpoints that are cheap to call but costly to abuse,
+-- keyed by e.g. `siwe_nonce:<address>`. Each counter covers a fixed window.
+CREATE TABLE rate_limits (
+    key VARCHAR(320) PRIMARY KEY,
+    hits INTEGER NOT NULL DEFAULT 0,
+    window_started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,28 @@
+use crate::config::ConfigError;
+use std::env;
+
+#[derive(Clone)]
+pub struct AccessTokenConfig {
+    /// `iss` claim, e.g. `https://auth.example.com`.
+    pub issuer: String,
+    /// `aud` claim naming the services the tokens are meant for.
+    pub audience: String,
+    pub ttl_seconds: u64,
+}
+
+impl AccessTokenConfig {
+    /// Reads `JWT_ISSUER`, `JWT_AUDIENCE` and `ACCESS_TOKEN_TTL_SECONDS`. Issuer and
+    /// audience default to `app_base_url`.
+    pub fn from_env(app_base_url: &str) -> Result<Self, ConfigError> {
+        Ok(AccessTokenConfig {
+            issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| app_base_url.to_string()),
+            audience: env::var("JWT_AUDIENCE").unwrap_or_else(|_| app_base_url.to_string()),
+            ttl_seconds: match env::var("ACCESS_TOKEN_TTL_SECONDS") {
+                Ok(value) => value.parse().map_err(|_| {
+                    ConfigError::invalid("ACCESS_TOKEN_TTL_SECONDS", "must be an integer")
+                })?,
+                Err(_) => 60 * 60,
+            },
+        })
+    }
+}
@@ -1,30 +1,102 @@
+use crate::config::{
+    AccessTokenConfig, ConfigError, JwtKeyConfig, MailConfig, OAuthProviderConfig,
+    PasswordPolicyConfig, TrustedProxyConfig,
+};
 use dotenv::dotenv;
+use std::collections::HashMap;
 use std::env;
 
 pub struct AppConfig {
     pub database_url: String,
     pub server_addr: String,
     pub jwt_secret: String,
-    pub oauth_client_id: String,
-    pub oauth_client_secret: String,
-    pub oauth_auth_url: String,
-    pub oauth_token_url: String,
-    pub oauth_redirect_url: String,
+    /// Asymmetric keys for access tokens. Tokens are signed with `jwt_secret` when empty.
+    pub jwt_keys: Vec<JwtKeyConfig>,
+    /// How long a key keeps verifying tokens after its successor becomes active.
+    pub jwt_key_grace_period_seconds: i64,
+    pub access_token: AccessTokenConfig,
+    pub oauth_providers: Vec<OAuthProviderConfig>,
+    pub siwe_domain: String,
+    pub siwe_uri: String,
+    pub siwe_rpc_urls: HashMap<u64, String>,
+    pub password_policy: PasswordPolicyConfig,
+    /// Public origin used to build links in emails, e.g. `https://auth.example.com`.
+    pub app_base_url: String,
+    pub mail: MailConfig,
+    pub require_email_verification: bool,
+    /// Name authenticator apps show next to TOTP codes.
+    pub totp_issuer: String,
+    /// Relying party name shown by browsers during passkey prompts.
+    pub webauthn_rp_name: String,
+    pub trusted_proxies: TrustedProxyConfig,
 }
 
 impl AppConfig {
-    pub fn from_env() -> Result<Self, env::VarError> {
+    pub fn from_env() -> Result<Self, ConfigError> {
         dotenv().ok();
 
+        // e.g. OAUTH_PROVIDERS=google,github,keycloak
+        let oauth_providers = env::var("OAUTH_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(OAuthProviderConfig::from_env)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // e.g. JWT_KEYS=2024-07,2024-10
+        let jwt_keys = env::var("JWT_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|kid| !kid.is_empty())
+            .map(JwtKeyConfig::from_env)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let app_base_url = env::var("APP_BASE_URL")?;
+
+        // e.g. SIWE_RPC_URLS=1=https://eth.example.com,137=https://polygon.example.com
+        let siwe_rpc_urls = env::var("SIWE_RPC_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (chain_id, url) = entry.split_once('=').ok_or_else(|| {
+                    ConfigError::invalid("SIWE_RPC_URLS", "entries must look like <chain_id>=<url>")
+                })?;
+                let chain_id = chain_id.trim().parse().map_err(|_| {
+                    ConfigError::invalid("SIWE_RPC_URLS", "chain IDs must be integers")
+                })?;
+                Ok((chain_id, url.trim().to_string()))
+            })
+            .collect::<Result<HashMap<_, _>, ConfigError>>()?;
+
         Ok(AppConfig {
             database_url: env::var("DATABASE_URL")?,
             server_addr: env::var("SERVER_ADDR").unwrap_or_else(|_| "0.0.0.0:3000".to_string()),
             jwt_secret: env::var("JWT_SECRET")?,
-            oauth_client_id: env::var("OAUTH_CLIENT_ID")?,
-            oauth_client_secret: env::var("OAUTH_CLIENT_SECRET")?,
-            oauth_auth_url: env::var("OAUTH_AUTH_URL")?,
-            oauth_token_url: env::var("OAUTH_TOKEN_URL")?,
-            oauth_redirect_url: env::var("OAUTH_REDIRECT_URL")?,
+            jwt_keys,
+            jwt_key_grace_period_seconds: match env::var("JWT_KEY_GRACE_PERIOD_SECONDS") {
+                Ok(value) => value.parse().map_err(|_| {
+                    ConfigError::invalid("JWT_KEY_GRACE_PERIOD_SECONDS", "must be an integer")
+                })?,
+                Err(_) => 24 * 60 * 60,
+            },
+            access_token: AccessTokenConfig::from_env(&app_base_url)?,
+            oauth_providers,
+            siwe_domain: env::var("SIWE_DOMAIN")?,
+            siwe_uri: env::var("SIWE_URI")?,
+            siwe_rpc_urls,
+            password_policy: PasswordPolicyConfig::from_env()?,
+            app_base_url,
+            mail: MailConfig::from_env()?,
+            require_email_verification: env::var("REQUIRE_EMAIL_VERIFICATION")
+                .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false),
+            totp_issuer: env::var("TOTP_ISSUER").unwrap_or_else(|_| "My App".to_string()),
+            webauthn_rp_name: env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "My App".to_string()),
+            trusted_proxies: TrustedProxyConfig::from_env()?,
         })
     }
 }
@@ -0,0 +1,42 @@
+use crate::config::ConfigError;
+use std::env;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Clone)]
+pub struct JwtKeyConfig {
+    /// Published as `kid` in token headers and the JWKS document.
+    pub kid: String,
+    /// One of `RS256`, `ES256` or `EdDSA`.
+    pub algorithm: String,
+    /// PKCS#8 PEM file holding the private key.
+    pub private_key_path: String,
+    /// When the key starts signing tokens. The key it replaces keeps verifying
+    /// for the grace period after this.
+    pub active_from: OffsetDateTime,
+}
+
+impl JwtKeyConfig {
+    /// Reads `JWT_KEY_<KID>_*` variables for the key called `kid`.
+    pub fn from_env(kid: &str) -> Result<Self, ConfigError> {
+        let prefix = format!("JWT_KEY_{}", kid.to_uppercase().replace(['-', '.'], "_"));
+        let var = |key: &str| env::var(format!("{prefix}_{key}"));
+
+        let active_from = match var("ACTIVE_FROM") {
+            Ok(value) => OffsetDateTime::parse(&value, &Rfc3339).map_err(|_| {
+                ConfigError::invalid(
+                    format!("{prefix}_ACTIVE_FROM"),
+                    "must be an RFC 3339 timestamp",
+                )
+            })?,
+            Err(_) => OffsetDateTime::UNIX_EPOCH,
+        };
+
+        Ok(JwtKeyConfig {
+            kid: kid.to_string(),
+            algorithm: var("ALGORITHM")?,
+            private_key_path: var("PRIVATE_KEY_PATH")?,
+            active_from,
+        })
+    }
+}
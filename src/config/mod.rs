@@ -1,3 +1,17 @@
+mod access_token_config;
 mod app_config;
+mod config_error;
+mod jwt_key_config;
+mod mail_config;
+mod oauth_config;
+mod password_policy_config;
+mod trusted_proxy_config;
 
+pub use access_token_config::AccessTokenConfig;
 pub use app_config::AppConfig;
+pub use config_error::ConfigError;
+pub use jwt_key_config::JwtKeyConfig;
+pub use mail_config::MailConfig;
+pub use oauth_config::{OAuthProviderConfig, UserInfoMapping};
+pub use password_policy_config::PasswordPolicyConfig;
+pub use trusted_proxy_config::TrustedProxyConfig;
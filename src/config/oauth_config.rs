@@ -0,0 +1,66 @@
+use std::env;
+
+/// Which userinfo fields carry the identity, since providers name them differently.
+#[derive(Clone)]
+pub struct UserInfoMapping {
+    pub subject: String,
+    pub email: String,
+    pub email_verified: String,
+    pub username: String,
+}
+
+#[derive(Clone)]
+pub struct OAuthProviderConfig {
+    pub name: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// When set, the provider is treated as OpenID Connect and the endpoints
+    /// below default to those from its discovery document.
+    pub issuer: Option<String>,
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
+    pub redirect_url: String,
+    pub userinfo_url: Option<String>,
+    pub scopes: Vec<String>,
+    pub userinfo_mapping: UserInfoMapping,
+}
+
+impl OAuthProviderConfig {
+    /// Reads `OAUTH_<NAME>_*` variables for the provider called `name`.
+    pub fn from_env(name: &str) -> Result<Self, env::VarError> {
+        let prefix = format!("OAUTH_{}", name.to_uppercase().replace('-', "_"));
+        let var = |key: &str| env::var(format!("{prefix}_{key}"));
+        let var_or = |key: &str, default: &str| var(key).unwrap_or_else(|_| default.to_string());
+
+        let issuer = var("ISSUER").ok();
+        // Plain OAuth2 providers have no discovery document to fall back on.
+        let endpoint = |key: &str| {
+            if issuer.is_some() {
+                Ok(var(key).ok())
+            } else {
+                var(key).map(Some)
+            }
+        };
+
+        Ok(OAuthProviderConfig {
+            name: name.to_string(),
+            client_id: var("CLIENT_ID")?,
+            client_secret: var("CLIENT_SECRET")?,
+            issuer: issuer.clone(),
+            auth_url: endpoint("AUTH_URL")?,
+            token_url: endpoint("TOKEN_URL")?,
+            redirect_url: var("REDIRECT_URL")?,
+            userinfo_url: endpoint("USERINFO_URL")?,
+            scopes: var_or("SCOPES", "openid email profile")
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            userinfo_mapping: UserInfoMapping {
+                subject: var_or("SUBJECT_FIELD", "sub"),
+                email: var_or("EMAIL_FIELD", "email"),
+                email_verified: var_or("EMAIL_VERIFIED_FIELD", "email_verified"),
+                username: var_or("USERNAME_FIELD", "preferred_username"),
+            },
+        })
+    }
+}
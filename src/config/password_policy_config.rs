@@ -0,0 +1,45 @@
+use crate::config::ConfigError;
+use std::env;
+
+#[derive(Clone)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Directory of Pwned Passwords range files: one `<PREFIX>.txt` per 5-character
+    /// SHA-1 prefix, holding `<SUFFIX>:<COUNT>` lines. The check is skipped when unset.
+    pub breached_passwords_dir: Option<String>,
+    /// Accept the password when its range file cannot be read, rather than refusing it.
+    pub breached_passwords_fail_open: bool,
+}
+
+impl PasswordPolicyConfig {
+    /// Reads `PASSWORD_*` variables, falling back to defaults for any that are unset.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let number = |key: &str, default: usize| match env::var(key) {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| ConfigError::invalid(key, "must be an integer")),
+            Err(_) => Ok(default),
+        };
+        let flag = |key: &str, default: bool| {
+            env::var(key)
+                .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+                .unwrap_or(default)
+        };
+
+        Ok(PasswordPolicyConfig {
+            min_length: number("PASSWORD_MIN_LENGTH", 12)?,
+            max_length: number("PASSWORD_MAX_LENGTH", 128)?,
+            require_lowercase: flag("PASSWORD_REQUIRE_LOWERCASE", true),
+            require_uppercase: flag("PASSWORD_REQUIRE_UPPERCASE", true),
+            require_digit: flag("PASSWORD_REQUIRE_DIGIT", true),
+            require_symbol: flag("PASSWORD_REQUIRE_SYMBOL", false),
+            breached_passwords_dir: env::var("PASSWORD_BREACHED_CORPUS_DIR").ok(),
+            breached_passwords_fail_open: flag("PASSWORD_BREACHED_FAIL_OPEN", false),
+        })
+    }
+}
@@ -0,0 +1,62 @@
+use crate::config::ConfigError;
+use std::env;
+use std::net::IpAddr;
+
+/// Reverse proxies allowed to name the client through `X-Forwarded-For`.
+#[derive(Clone, Default)]
+pub struct TrustedProxyConfig {
+    /// Network address and prefix length pairs.
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxyConfig {
+    /// Reads `TRUSTED_PROXIES`, e.g. `10.0.0.0/8,::1`. Forwarding headers are
+    /// ignored when it is unset.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let networks = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                parse_network(entry).ok_or_else(|| {
+                    ConfigError::invalid(
+                        "TRUSTED_PROXIES",
+                        format!("{entry} is not an IP address or CIDR range"),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TrustedProxyConfig { networks })
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.networks
+            .iter()
+            .any(|&(network, prefix)| match (network, ip) {
+                (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                    let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+                    u32::from(network) & mask == u32::from(ip) & mask
+                }
+                (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                    let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+                    u128::from(network) & mask == u128::from(ip) & mask
+                }
+                _ => false,
+            })
+    }
+}
+
+fn parse_network(entry: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match entry.split_once('/') {
+        Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, Some(prefix.parse().ok()?)),
+        None => (entry.parse::<IpAddr>().ok()?, None),
+    };
+    let address = address.to_canonical();
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max_prefix);
+
+    (prefix <= max_prefix).then_some((address, prefix))
+}
@@ -1,5 +1,6 @@
+use crate::models::FieldError;
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -14,8 +15,24 @@ pub enum AppError {
     BadRequest(String),
     #[error("Unauthorized")]
     Unauthorized,
+    #[error("Validation failed")]
+    Validation(Vec<FieldError>),
+    #[error("Email address has not been verified")]
+    EmailNotVerified,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Too many requests, retry in {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
+    /// An RFC 6749 error response, e.g. `invalid_grant`.
+    #[error("{error}: {description}")]
+    OAuth2 {
+        error: &'static str,
+        description: String,
+    },
     #[error("Not found")]
     NotFound,
+    #[error("Invalid CSRF token")]
+    CsrfTokenMismatch,
     #[error("Database error: {0}")]
     DatabaseError(#[from] sqlx::Error),
     #[error("JWT error: {0}")]
@@ -26,19 +43,61 @@ pub enum AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let fields = match &self {
+            AppError::Validation(errors) => Some(errors.clone()),
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             AppError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
+            AppError::Validation(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
+            AppError::EmailNotVerified => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::TooManyRequests { retry_after } => {
+                let body = Json(json!({
+                    "error": self.to_string(),
+                }));
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    body,
+                )
+                    .into_response();
+            }
+            AppError::OAuth2 { error, description } => {
+                let body = Json(json!({
+                    "error": error,
+                    "error_description": description,
+                }));
+                // Clients that failed to authenticate are told how to, per RFC 6749 section 5.2.
+                if error == "invalid_client" {
+                    return (
+                        StatusCode::UNAUTHORIZED,
+                        [(header::WWW_AUTHENTICATE, "Basic")],
+                        body,
+                    )
+                        .into_response();
+                }
+                return (StatusCode::BAD_REQUEST, body).into_response();
+            }
             AppError::NotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::CsrfTokenMismatch => (StatusCode::FORBIDDEN, self.to_string()),
             AppError::DatabaseError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::JWTError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::EnvVarError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
         };
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+        let body = match fields {
+            Some(fields) => Json(json!({
+                "error": error_message,
+                "fields": fields,
+            })),
+            None => Json(json!({
+                "error": error_message,
+            })),
+        };
 
         (status, body).into_response()
     }
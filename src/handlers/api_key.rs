@@ -0,0 +1,72 @@
+use crate::error::AppError;
+use crate::handlers::auth::is_htmx;
+use crate::middleware::{AuthUser, PageContext};
+use crate::models::CreateApiKeyRequest;
+use crate::routes::api_v1::AppState;
+use crate::services::ApiKeyService;
+use crate::templates::{
+    ApiKeyCreatedTemplate, ApiKeyForm, ApiKeyFormTemplate, ApiKeysTemplate, FieldErrors,
+};
+use askama_axum::IntoResponse;
+use askama_axum::Template;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, Response};
+use axum::{Form, Json};
+
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    ctx: PageContext,
+) -> Result<impl IntoResponse, AppError> {
+    let keys = state.api_key_service.list_keys(user.id).await?;
+    let template = ApiKeysTemplate {
+        ctx,
+        keys,
+        form: ApiKeyForm::default(),
+    };
+    Ok(Html(template.render().unwrap()))
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    headers: HeaderMap,
+    Form(req): Form<CreateApiKeyRequest>,
+) -> Result<Response, AppError> {
+    let scopes: Vec<String> = req.scopes.split_whitespace().map(str::to_string).collect();
+
+    let new_key = match state
+        .api_key_service
+        .create_key(user.id, &req.name, &scopes, req.expires_in_days)
+        .await
+    {
+        Ok(new_key) => new_key,
+        Err(AppError::Validation(errors)) if is_htmx(&headers) => {
+            let template = ApiKeyFormTemplate {
+                form: ApiKeyForm {
+                    name: req.name,
+                    scopes: req.scopes,
+                    errors: FieldErrors(errors),
+                },
+            };
+            return Ok(Html(template.render().unwrap()).into_response());
+        }
+        Err(e) => return Err(e),
+    };
+
+    if is_htmx(&headers) {
+        let template = ApiKeyCreatedTemplate { new_key };
+        return Ok(Html(template.render().unwrap()).into_response());
+    }
+    Ok((StatusCode::CREATED, Json(new_key)).into_response())
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    state.api_key_service.revoke_key(user.id, id).await?;
+    Ok("") // HTMX removes the key's row
+}
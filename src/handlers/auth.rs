@@ -1,43 +1,244 @@
 use crate::error::AppError;
-use crate::models::auth::{AuthResponse, LoginRequest, RegisterRequest};
+use crate::middleware::auth::cookie_value;
+use crate::middleware::{
+    clear_session_cookie, session_cookie, AuthToken, AuthUser, ClientIp, PageContext,
+};
+use crate::models::{
+    AuthResponse, LoginQuery, LoginRequest, LoginResult, MfaLoginRequest, RefreshRequest,
+    RegisterRequest, ResendVerificationRequest, VerifyEmailQuery,
+};
 use crate::routes::api_v1::AppState;
-use crate::services::{AuthService, OAuthService, SiweService};
-use crate::templates::{LoginTemplate, RegisterTemplate};
+use crate::services::{AuthService, EmailVerificationService, OAuthService, SiweService};
+use crate::templates::{
+    FieldErrors, LoginTemplate, MfaFormTemplate, RegisterForm, RegisterFormTemplate,
+    RegisterTemplate, VerifyEmailTemplate,
+};
+use crate::utils::crypto::constant_time_eq;
 use askama_axum::IntoResponse;
 use askama_axum::Template;
-use axum::{extract::Query, Json};
+use axum::http::{header, HeaderMap, HeaderName};
+use axum::response::{AppendHeaders, Redirect, Response};
 use axum::{extract::State, response::Html, Form};
-use serde::Deserialize;
+use axum::{
+    extract::{Path, Query},
+    Json,
+};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-pub async fn show_register() -> impl IntoResponse {
-    let template = RegisterTemplate {};
+pub(crate) const HX_REDIRECT: HeaderName = HeaderName::from_static("hx-redirect");
+const OAUTH_STATE_COOKIE: &str = "oauth_state";
+const RESEND_NOTICE: &str =
+    "<p>If the address belongs to an unverified account, a verification link is on its way.</p>";
+
+pub(crate) fn is_htmx(headers: &HeaderMap) -> bool {
+    headers.contains_key("hx-request")
+}
+
+/// `next` if it is a path on this site, so a crafted login link cannot send the user elsewhere.
+///
+/// Browsers drop control characters and treat `\` like `/`, so `/\t/evil.com` would
+/// still turn into `//evil.com`; paths containing either are refused outright.
+fn local_path(next: Option<&str>) -> &str {
+    match next {
+        Some(path)
+            if path.starts_with('/')
+                && !path.starts_with("//")
+                && !path.chars().any(|c| c.is_control() || c == '\\') =>
+        {
+            path
+        }
+        _ => "/",
+    }
+}
+
+/// Starts a browser session and tells HTMX where to navigate next.
+fn session_redirect(token: &str, location: &str) -> Response {
+    (
+        [
+            (header::SET_COOKIE, session_cookie(token)),
+            (HX_REDIRECT, location.to_string()),
+        ],
+        "",
+    )
+        .into_response()
+}
+
+pub async fn show_register(ctx: PageContext) -> impl IntoResponse {
+    let template = RegisterTemplate {
+        ctx,
+        form: RegisterForm::default(),
+    };
     Html(template.render().unwrap())
 }
 
-pub async fn show_login() -> impl IntoResponse {
-    let template = LoginTemplate {};
+pub async fn show_login(
+    State(state): State<AppState>,
+    ctx: PageContext,
+    Query(query): Query<LoginQuery>,
+) -> impl IntoResponse {
+    let template = LoginTemplate {
+        ctx,
+        oauth_providers: state.oauth_service.provider_names(),
+        next: local_path(query.next.as_deref()).to_string(),
+    };
     Html(template.render().unwrap())
 }
 
 pub async fn register(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Form(req): Form<RegisterRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    let res = state.auth_service.register(req).await?;
-    Ok(format!("Registered successfully! Token: {}", res.token))
+) -> Result<Response, AppError> {
+    let (username, email) = (req.username.clone(), req.email.clone());
+    let res = match state.auth_service.register(req).await {
+        Ok(res) => res,
+        // HTMX swaps the form in place, so the errors are shown next to their fields.
+        Err(AppError::Validation(errors)) if is_htmx(&headers) => {
+            let template = RegisterFormTemplate {
+                form: RegisterForm {
+                    username,
+                    email,
+                    errors: FieldErrors(errors),
+                },
+            };
+            return Ok(Html(template.render().unwrap()).into_response());
+        }
+        Err(AppError::EmailNotVerified) if is_htmx(&headers) => {
+            let notice = "<p>Check your inbox for a link to verify your email address.</p>";
+            return Ok(Html(notice).into_response());
+        }
+        Err(e) => return Err(e),
+    };
+    if is_htmx(&headers) {
+        return Ok(session_redirect(&res.token, "/"));
+    }
+    Ok(Json(res).into_response())
 }
 
 pub async fn login(
     State(state): State<AppState>,
-    Form(req): Form<LoginRequest>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+    Form(mut req): Form<LoginRequest>,
+) -> Result<Response, AppError> {
+    let next = local_path(req.next.take().as_deref()).to_string();
+    let res = match state.auth_service.login(req, client_ip).await? {
+        LoginResult::Authenticated(res) => res,
+        LoginResult::MfaRequired(challenge) if is_htmx(&headers) => {
+            let template = MfaFormTemplate {
+                mfa_token: challenge.mfa_token,
+                error: None,
+                next,
+            };
+            return Ok(Html(template.render().unwrap()).into_response());
+        }
+        challenge @ LoginResult::MfaRequired(_) => return Ok(Json(challenge).into_response()),
+    };
+    if is_htmx(&headers) {
+        return Ok(session_redirect(&res.token, &next));
+    }
+    Ok(Json(res).into_response())
+}
+
+pub async fn login_mfa(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+    Form(req): Form<MfaLoginRequest>,
+) -> Result<Response, AppError> {
+    let res = match state
+        .auth_service
+        .verify_mfa(&req.mfa_token, &req.code, client_ip)
+        .await
+    {
+        Ok(res) => res,
+        Err(e @ (AppError::Unauthorized | AppError::TooManyRequests { .. }))
+            if is_htmx(&headers) =>
+        {
+            let error = match &e {
+                AppError::TooManyRequests { .. } => e.to_string(),
+                _ => "Invalid or expired code".to_string(),
+            };
+            let template = MfaFormTemplate {
+                mfa_token: req.mfa_token,
+                error: Some(error),
+                next: local_path(req.next.as_deref()).to_string(),
+            };
+            return Ok(Html(template.render().unwrap()).into_response());
+        }
+        Err(e) => return Err(e),
+    };
+    if is_htmx(&headers) {
+        return Ok(session_redirect(
+            &res.token,
+            local_path(req.next.as_deref()),
+        ));
+    }
+    Ok(Json(res).into_response())
+}
+
+pub async fn verify_email(
+    State(state): State<AppState>,
+    ctx: PageContext,
+    Query(query): Query<VerifyEmailQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let res = state.auth_service.login(req).await?;
-    Ok(format!("Logged in successfully! Token: {}", res.token))
+    let verified = match state.email_verification_service.verify(&query.token).await {
+        Ok(()) => true,
+        Err(AppError::BadRequest(_)) => false,
+        Err(e) => return Err(e),
+    };
+    let template = VerifyEmailTemplate { ctx, verified };
+    Ok(Html(template.render().unwrap()))
 }
 
-pub async fn logout() -> impl IntoResponse {
-    "Logged out successfully"
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    Form(req): Form<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .email_verification_service
+        .resend(&req.email, client_ip)
+        .await?;
+    Ok(Html(RESEND_NOTICE))
+}
+
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    Json(req): Json<RefreshRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    let res = state.auth_service.refresh(req).await?;
+    Ok(Json(res))
+}
+
+pub async fn logout(
+    State(state): State<AppState>,
+    token: Option<AuthToken>,
+) -> Result<impl IntoResponse, AppError> {
+    if let Some(AuthToken(token)) = token {
+        // An already expired or revoked token still gets its cookie cleared.
+        match state.auth_service.logout(&token).await {
+            Ok(()) | Err(AppError::Unauthorized) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok((
+        [
+            (header::SET_COOKIE, clear_session_cookie()),
+            (HX_REDIRECT, "/login".to_string()),
+        ],
+        "Logged out successfully",
+    ))
+}
+
+pub async fn logout_all(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+) -> Result<impl IntoResponse, AppError> {
+    state.auth_service.logout_all(user.id).await?;
+    Ok("Logged out of all sessions")
 }
 
 #[derive(Deserialize)]
@@ -46,17 +247,67 @@ pub struct OAuthCallback {
     state: String,
 }
 
-pub async fn oauth_login(State(state): State<AppState>) -> Result<String, AppError> {
-    let (auth_url, _csrf_token) = state.oauth_service.get_authorize_url();
-    Ok(auth_url)
+pub async fn oauth_login(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let (auth_url, csrf_token) = state
+        .oauth_service
+        .get_authorize_url(&provider, client_ip)
+        .await?;
+
+    // Binds the pending authorization to this browser so a callback carrying
+    // someone else's state is rejected.
+    let state_cookie = format!(
+        "{OAUTH_STATE_COOKIE}={}; Path=/oauth; HttpOnly; Secure; SameSite=Lax; Max-Age=600",
+        csrf_token.secret()
+    );
+
+    if is_htmx(&headers) {
+        return Ok((
+            [(header::SET_COOKIE, state_cookie), (HX_REDIRECT, auth_url)],
+            "",
+        )
+            .into_response());
+    }
+    Ok((
+        [(header::SET_COOKIE, state_cookie)],
+        Redirect::to(&auth_url),
+    )
+        .into_response())
 }
 
 pub async fn oauth_callback(
     State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
     Query(params): Query<OAuthCallback>,
-) -> Result<Json<AuthResponse>, AppError> {
-    let token = state.oauth_service.exchange_code(params.code).await?;
-    Ok(Json(AuthResponse { token }))
+) -> Result<impl IntoResponse, AppError> {
+    let expected_state = cookie_value(&headers, OAUTH_STATE_COOKIE)
+        .ok_or_else(|| AppError::BadRequest("Missing OAuth state".to_string()))?;
+    if !constant_time_eq(&expected_state, &params.state) {
+        return Err(AppError::BadRequest(
+            "Invalid or expired OAuth state".to_string(),
+        ));
+    }
+
+    let identity = state
+        .oauth_service
+        .exchange_code(&provider, params.code, params.state)
+        .await?;
+    let res = state.auth_service.login_with_identity(identity).await?;
+    let clear_state_cookie =
+        format!("{OAUTH_STATE_COOKIE}=; Path=/oauth; HttpOnly; Secure; SameSite=Lax; Max-Age=0");
+
+    Ok((
+        AppendHeaders([
+            (header::SET_COOKIE, session_cookie(&res.token)),
+            (header::SET_COOKIE, clear_state_cookie),
+        ]),
+        Redirect::to("/"),
+    ))
 }
 
 #[derive(Deserialize)]
@@ -65,17 +316,51 @@ pub struct SiweRequest {
     signature: String,
 }
 
+#[derive(Serialize)]
+pub struct SiweNonceResponse {
+    nonce: String,
+}
+
+pub async fn siwe_nonce(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Json<SiweNonceResponse>, AppError> {
+    let nonce = state.siwe_service.issue_nonce(client_ip).await?;
+    Ok(Json(SiweNonceResponse { nonce }))
+}
+
 pub async fn siwe_login(
     State(state): State<AppState>,
     Json(req): Json<SiweRequest>,
-) -> Result<Json<AuthResponse>, AppError> {
+) -> Result<impl IntoResponse, AppError> {
     let address = state
         .siwe_service
         .verify_signature(req.message, req.signature)
         .await?;
-    // Here you would typically create or fetch a user based on the Ethereum address
-    // and generate a JWT token. For simplicity, we're just returning the address as the token.
-    Ok(Json(AuthResponse {
-        token: address.to_string(),
-    }))
+    let res = state
+        .auth_service
+        .login_with_wallet(&format!("{:#x}", address))
+        .await?;
+
+    Ok((
+        [(header::SET_COOKIE, session_cookie(&res.token))],
+        Json(res),
+    ))
+}
+
+pub async fn siwe_link(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Json(req): Json<SiweRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let address = state
+        .siwe_service
+        .verify_signature(req.message, req.signature)
+        .await?;
+    state
+        .auth_service
+        .link_wallet(user.id, &format!("{:#x}", address))
+        .await?;
+
+    Ok("Wallet linked successfully")
 }
@@ -1,4 +1,10 @@
+pub mod api_key;
 pub mod auth;
 pub mod health;
+pub mod oauth2;
+pub mod password;
 pub mod product;
+pub mod two_factor;
 pub mod user;
+pub mod webauthn;
+pub mod well_known;
@@ -0,0 +1,329 @@
+use crate::error::AppError;
+use crate::handlers::auth::{is_htmx, HX_REDIRECT};
+use crate::middleware::{AuthUser, ClientIp, DelegatedScopes, PageContext};
+use crate::models::{
+    AuthorizationError, AuthorizeQuery, ClientCredentials, ConsentRequest,
+    CreateOAuth2ClientRequest, DeviceAuthorizationRequest, DeviceQuery, DeviceVerificationRequest,
+    TokenOperationRequest, TokenRequest, UserInfoResponse,
+};
+use crate::routes::api_v1::AppState;
+use crate::services::OAuth2ServerService;
+use crate::templates::{
+    DeviceConfirmTemplate, DeviceFormTemplate, DeviceTemplate, OAuth2ConsentTemplate,
+};
+use askama_axum::IntoResponse;
+use askama_axum::Template;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::{Html, Redirect, Response};
+use axum::{Extension, Form, Json};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use url::form_urlencoded;
+
+const DEVICE_APPROVED: &str =
+    "<p>The device is now signed in. You can close this page and return to it.</p>";
+const DEVICE_DENIED: &str = "<p>The device was not signed in. You can close this page.</p>";
+const INVALID_USER_CODE: &str = "That code is invalid or has expired";
+
+/// Sends the user to sign in first, then back to the page they asked for.
+fn login_redirect(uri: &Uri) -> Response {
+    let next: String = form_urlencoded::byte_serialize(uri.to_string().as_bytes()).collect();
+    Redirect::to(&format!("/login?next={next}")).into_response()
+}
+
+/// Sends the browser back to the client, or shows the error here when the
+/// client's redirect URI cannot be trusted.
+fn authorization_error(err: AuthorizationError) -> Result<Response, AppError> {
+    match err {
+        AuthorizationError::Invalid(e) => Err(e),
+        AuthorizationError::Redirect(url) => Ok(Redirect::to(&url).into_response()),
+    }
+}
+
+/// Client credentials from HTTP Basic auth, falling back to the request body.
+fn client_credentials(
+    headers: &HeaderMap,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> Option<ClientCredentials> {
+    if let Some(value) = headers.get(header::AUTHORIZATION) {
+        let encoded = value.to_str().ok()?.strip_prefix("Basic ")?;
+        let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+        let (client_id, client_secret) = decoded.split_once(':')?;
+        return Some(ClientCredentials {
+            client_id: client_id.to_string(),
+            client_secret: Some(client_secret.to_string()).filter(|secret| !secret.is_empty()),
+        });
+    }
+
+    client_id.map(|client_id| ClientCredentials {
+        client_id,
+        client_secret,
+    })
+}
+
+pub async fn authorize(
+    State(state): State<AppState>,
+    ctx: PageContext,
+    delegated: Option<Extension<DelegatedScopes>>,
+    uri: Uri,
+    Query(query): Query<AuthorizeQuery>,
+) -> Result<Response, AppError> {
+    // Granting access on the user's behalf needs the user, not one of their scripts.
+    if delegated.is_some() {
+        return Err(AppError::Forbidden);
+    }
+    if ctx.current_user.is_none() {
+        return Ok(login_redirect(&uri));
+    }
+
+    let request = match state
+        .oauth2_server_service
+        .validate_authorization(&query)
+        .await
+    {
+        Ok(request) => request,
+        Err(e) => return authorization_error(e),
+    };
+
+    let template = OAuth2ConsentTemplate {
+        ctx,
+        client_name: request.client.name,
+        scopes: request.scopes,
+        query,
+    };
+    Ok(Html(template.render().unwrap()).into_response())
+}
+
+pub async fn consent(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    delegated: Option<Extension<DelegatedScopes>>,
+    headers: HeaderMap,
+    Form(req): Form<ConsentRequest>,
+) -> Result<Response, AppError> {
+    if delegated.is_some() {
+        return Err(AppError::Forbidden);
+    }
+
+    // The form only echoes the original request, so it is validated again.
+    let request = match state
+        .oauth2_server_service
+        .validate_authorization(&req.query)
+        .await
+    {
+        Ok(request) => request,
+        Err(e) => return authorization_error(e),
+    };
+    let location = if req.decision == "allow" {
+        state
+            .oauth2_server_service
+            .approve(user.id, &request)
+            .await?
+    } else {
+        state.oauth2_server_service.deny(&request)
+    };
+
+    if is_htmx(&headers) {
+        return Ok(([(HX_REDIRECT, location)], "").into_response());
+    }
+    Ok(Redirect::to(&location).into_response())
+}
+
+pub async fn token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(mut req): Form<TokenRequest>,
+) -> Result<Response, AppError> {
+    let credentials = client_credentials(&headers, req.client_id.take(), req.client_secret.take());
+    let res = state
+        .oauth2_server_service
+        .exchange(credentials, req)
+        .await?;
+
+    Ok((
+        [
+            (header::CACHE_CONTROL, "no-store"),
+            (header::PRAGMA, "no-cache"),
+        ],
+        Json(res),
+    )
+        .into_response())
+}
+
+pub async fn userinfo(
+    AuthUser(user): AuthUser,
+    delegated: Option<Extension<DelegatedScopes>>,
+) -> impl IntoResponse {
+    let share_email = match delegated {
+        Some(Extension(DelegatedScopes(scopes))) => scopes.iter().any(|scope| scope == "email"),
+        None => true,
+    };
+    let (email, email_verified) = match user.email {
+        Some(email) if share_email => (Some(email), Some(user.email_verified_at.is_some())),
+        _ => (None, None),
+    };
+
+    Json(UserInfoResponse {
+        sub: user.id.to_string(),
+        preferred_username: user.username,
+        email,
+        email_verified,
+    })
+}
+
+pub async fn device_authorization(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(req): Form<DeviceAuthorizationRequest>,
+) -> Result<Response, AppError> {
+    let credentials = client_credentials(&headers, req.client_id, req.client_secret);
+    let res = state
+        .oauth2_server_service
+        .start_device_authorization(credentials, req.scope.as_deref())
+        .await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(res)).into_response())
+}
+
+pub async fn show_device(
+    ctx: PageContext,
+    delegated: Option<Extension<DelegatedScopes>>,
+    uri: Uri,
+    Query(query): Query<DeviceQuery>,
+) -> Result<Response, AppError> {
+    if delegated.is_some() {
+        return Err(AppError::Forbidden);
+    }
+    if ctx.current_user.is_none() {
+        return Ok(login_redirect(&uri));
+    }
+
+    let template = DeviceTemplate {
+        ctx,
+        user_code: query.user_code.unwrap_or_default(),
+        error: None,
+    };
+    Ok(Html(template.render().unwrap()).into_response())
+}
+
+/// Shows the request behind the entered code, then records the user's decision on it.
+pub async fn verify_device(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    ClientIp(client_ip): ClientIp,
+    delegated: Option<Extension<DelegatedScopes>>,
+    Form(req): Form<DeviceVerificationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if delegated.is_some() {
+        return Err(AppError::Forbidden);
+    }
+
+    let result = match req.decision.as_deref() {
+        None => state
+            .oauth2_server_service
+            .pending_device_authorization(user.id, client_ip, &req.user_code)
+            .await
+            .map(|pending| {
+                DeviceConfirmTemplate {
+                    client_name: pending.client.name,
+                    user_code: pending.user_code,
+                    scopes: pending.scopes,
+                }
+                .render()
+                .unwrap()
+            }),
+        Some(decision) => {
+            let approved = decision == "allow";
+            state
+                .oauth2_server_service
+                .decide_device_authorization(user.id, client_ip, &req.user_code, approved)
+                .await
+                .map(|()| {
+                    if approved {
+                        DEVICE_APPROVED
+                    } else {
+                        DEVICE_DENIED
+                    }
+                    .to_string()
+                })
+        }
+    };
+
+    match result {
+        Ok(html) => Ok(Html(html)),
+        Err(e @ (AppError::NotFound | AppError::TooManyRequests { .. })) => {
+            let error = match &e {
+                AppError::TooManyRequests { .. } => e.to_string(),
+                _ => INVALID_USER_CODE.to_string(),
+            };
+            let template = DeviceFormTemplate {
+                user_code: req.user_code,
+                error: Some(error),
+            };
+            Ok(Html(template.render().unwrap()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(req): Form<TokenOperationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let credentials = client_credentials(&headers, req.client_id, req.client_secret);
+    let res = state
+        .oauth2_server_service
+        .introspect(credentials, &req.token)
+        .await?;
+    Ok(Json(res))
+}
+
+pub async fn revoke(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(req): Form<TokenOperationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let credentials = client_credentials(&headers, req.client_id, req.client_secret);
+    state
+        .oauth2_server_service
+        .revoke(credentials, &req.token)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn list_clients(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let clients = state.oauth2_server_service.list_clients().await?;
+    Ok(Json(clients))
+}
+
+pub async fn create_client(
+    State(state): State<AppState>,
+    Form(req): Form<CreateOAuth2ClientRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let redirect_uris: Vec<String> = req
+        .redirect_uris
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    let scopes: Vec<String> = req.scopes.split_whitespace().map(str::to_string).collect();
+
+    let new_client = state
+        .oauth2_server_service
+        .register_client(&req.name, &redirect_uris, &scopes, req.confidential)
+        .await?;
+    Ok((StatusCode::CREATED, Json(new_client)))
+}
+
+pub async fn delete_client(
+    State(state): State<AppState>,
+    Path(client_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .oauth2_server_service
+        .delete_client(&client_id)
+        .await?;
+    Ok("Client deleted")
+}
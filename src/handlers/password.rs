@@ -0,0 +1,87 @@
+use crate::error::AppError;
+use crate::handlers::auth::is_htmx;
+use crate::middleware::{ClientIp, PageContext};
+use crate::models::{FieldError, ForgotPasswordRequest, ResetPasswordQuery, ResetPasswordRequest};
+use crate::routes::api_v1::AppState;
+use crate::services::PasswordResetService;
+use crate::templates::{
+    FieldErrors, ForgotPasswordTemplate, ResetPasswordForm, ResetPasswordFormTemplate,
+    ResetPasswordTemplate,
+};
+use askama_axum::IntoResponse;
+use askama_axum::Template;
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::{Html, Response};
+use axum::Form;
+
+const FORGOT_NOTICE: &str =
+    "<p>If an account uses that address, a link to reset its password is on its way.</p>";
+const RESET_NOTICE: &str = r#"<p>Your password has been reset. <a class="link" href="/login">Login</a> with your new password.</p>"#;
+
+pub async fn show_forgot_password(ctx: PageContext) -> impl IntoResponse {
+    let template = ForgotPasswordTemplate { ctx };
+    Html(template.render().unwrap())
+}
+
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    Form(req): Form<ForgotPasswordRequest>,
+) -> impl IntoResponse {
+    // Runs in the background so the response takes the same time whether or
+    // not the address is registered, or the request was over its rate limit.
+    let password_reset_service = state.password_reset_service.clone();
+    tokio::spawn(async move {
+        match password_reset_service
+            .request_reset(&req.email, client_ip)
+            .await
+        {
+            Ok(()) | Err(AppError::TooManyRequests { .. }) => {}
+            Err(e) => tracing::warn!("failed to send password reset email: {}", e),
+        }
+    });
+
+    Html(FORGOT_NOTICE)
+}
+
+pub async fn show_reset_password(
+    ctx: PageContext,
+    Query(query): Query<ResetPasswordQuery>,
+) -> impl IntoResponse {
+    let template = ResetPasswordTemplate {
+        ctx,
+        form: ResetPasswordForm {
+            token: query.token,
+            errors: FieldErrors::default(),
+        },
+    };
+    Html(template.render().unwrap())
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(req): Form<ResetPasswordRequest>,
+) -> Result<Response, AppError> {
+    let errors = match state
+        .password_reset_service
+        .reset_password(&req.token, &req.password)
+        .await
+    {
+        Ok(()) => return Ok(Html(RESET_NOTICE).into_response()),
+        Err(AppError::Validation(errors)) if is_htmx(&headers) => errors,
+        Err(AppError::BadRequest(message)) if is_htmx(&headers) => {
+            vec![FieldError::new("token", message)]
+        }
+        Err(e) => return Err(e),
+    };
+
+    let template = ResetPasswordFormTemplate {
+        form: ResetPasswordForm {
+            token: req.token,
+            errors: FieldErrors(errors),
+        },
+    };
+    Ok(Html(template.render().unwrap()).into_response())
+}
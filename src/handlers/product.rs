@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
 use crate::error::AppError;
+use crate::middleware::PageContext;
 use crate::models::{BundleProduct, Product, ProductBundle};
 use crate::routes::api_v1::AppState;
 use crate::templates::{
@@ -20,38 +21,42 @@ use axum::{
 #[derive(Template)]
 #[template(path = "products.html")]
 struct ProductsTemplate {
+    ctx: PageContext,
     products: Vec<Product>,
 }
 
 #[derive(Template)]
 #[template(path = "product_bundles.html")]
 struct ProductBundlesTemplate {
+    ctx: PageContext,
     bundles: Vec<ProductBundle>,
 }
 
-pub async fn get_products(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+pub async fn get_products(State(state): State<AppState>, ctx: PageContext) -> Result<impl IntoResponse, AppError> {
     let products = state.product_service.get_all_products().await?;
-    let template = ProductListTemplate { products };
+    let template = ProductListTemplate { ctx, products };
     Ok(template)
 }
 
-pub async fn get_product(State(state): State<AppState>, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
+pub async fn get_product(State(state): State<AppState>, ctx: PageContext, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
     let product = state.product_service.get_product(id).await?;
-    let template = ProductDetailTemplate { product };
+    let template = ProductDetailTemplate { ctx, product };
     Ok(template)
 }
 
-pub async fn new_product() -> Result<impl IntoResponse, AppError> {
+pub async fn new_product(ctx: PageContext) -> Result<impl IntoResponse, AppError> {
     let template = ProductFormTemplate {
+        ctx,
         product: None,
         action: "post".to_string(),
     };
     Ok(template)
 }
 
-pub async fn edit_product(State(state): State<AppState>, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
+pub async fn edit_product(State(state): State<AppState>, ctx: PageContext, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
     let product = state.product_service.get_product(id).await?;
     let template = ProductFormTemplate {
+        ctx,
         product: Some(product),
         action: "put".to_string(),
     };
@@ -60,21 +65,23 @@ pub async fn edit_product(State(state): State<AppState>, Path(id): Path<i32>) ->
 
 pub async fn create_product(
     State(state): State<AppState>,
+    ctx: PageContext,
     Form(product): Form<Product>,
 ) -> Result<impl IntoResponse, AppError> {
     let created_product = state.product_service.create_product(product).await?;
-    let template = ProductDetailTemplate { product: created_product };
+    let template = ProductDetailTemplate { ctx, product: created_product };
     Ok(template)
 }
 
 pub async fn update_product(
     State(state): State<AppState>,
+    ctx: PageContext,
     Path(id): Path<i32>,
     Form(mut product): Form<Product>,
 ) -> Result<impl IntoResponse, AppError> {
     product.id = id;
     let updated_product = state.product_service.update_product(product).await?;
-    let template = ProductDetailTemplate { product: updated_product };
+    let template = ProductDetailTemplate { ctx, product: updated_product };
     Ok(template)
 }
 
@@ -89,22 +96,23 @@ pub struct BundleForm {
     quantities: Vec<i32>,
 }
 
-pub async fn get_bundles(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+pub async fn get_bundles(State(state): State<AppState>, ctx: PageContext) -> Result<impl IntoResponse, AppError> {
     let bundles = state.product_service.get_all_bundles().await?;
-    let template = BundleListTemplate { bundles };
+    let template = BundleListTemplate { ctx, bundles };
     Ok(template)
 }
 
-pub async fn get_bundle(State(state): State<AppState>, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
+pub async fn get_bundle(State(state): State<AppState>, ctx: PageContext, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
     let bundle = state.product_service.get_bundle(id).await?;
     let products = state.product_service.get_bundle_products(id).await?;
-    let template = BundleDetailTemplate { bundle, products };
+    let template = BundleDetailTemplate { ctx, bundle, products };
     Ok(template)
 }
 
-pub async fn new_bundle(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+pub async fn new_bundle(State(state): State<AppState>, ctx: PageContext) -> Result<impl IntoResponse, AppError> {
     let all_products = state.product_service.get_all_products().await?;
     let template = BundleFormTemplate {
+        ctx,
         bundle: None,
         all_products,
         selected_products: HashMap::new(),
@@ -113,13 +121,14 @@ pub async fn new_bundle(State(state): State<AppState>) -> Result<impl IntoRespon
     Ok(template)
 }
 
-pub async fn edit_bundle(State(state): State<AppState>, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
+pub async fn edit_bundle(State(state): State<AppState>, ctx: PageContext, Path(id): Path<i32>) -> Result<impl IntoResponse, AppError> {
     let bundle = state.product_service.get_bundle(id).await?;
     let all_products = state.product_service.get_all_products().await?;
     let bundle_products = state.product_service.get_bundle_products(id).await?;
     let selected_products: HashMap<i32, i32> = bundle_products.into_iter().map(|(p, q)| (p.id, q)).collect();
     
     let template = BundleFormTemplate {
+        ctx,
         bundle: Some(bundle),
         all_products,
         selected_products,
@@ -130,6 +139,7 @@ pub async fn edit_bundle(State(state): State<AppState>, Path(id): Path<i32>) ->
 
 pub async fn create_bundle(
     State(state): State<AppState>,
+    ctx: PageContext,
     Form(form): Form<BundleForm>,
 ) -> Result<impl IntoResponse, AppError> {
     let bundle_products: Vec<BundleProduct> = form.product_ids.into_iter()
@@ -138,12 +148,13 @@ pub async fn create_bundle(
         .collect();
     let created_bundle = state.product_service.create_bundle(form.bundle, bundle_products).await?;
     let bundle_products = state.product_service.get_bundle_products(created_bundle.id).await?;
-    let template = BundleDetailTemplate { bundle: created_bundle, products: bundle_products };
+    let template = BundleDetailTemplate { ctx, bundle: created_bundle, products: bundle_products };
     Ok(template)
 }
 
 pub async fn update_bundle(
     State(state): State<AppState>,
+    ctx: PageContext,
     Path(id): Path<i32>,
     Form(form): Form<BundleForm>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -155,7 +166,7 @@ pub async fn update_bundle(
         .collect();
     let updated_bundle = state.product_service.update_bundle(id, bundle, bundle_products).await?;
     let bundle_products = state.product_service.get_bundle_products(updated_bundle.id).await?;
-    let template = BundleDetailTemplate { bundle: updated_bundle, products: bundle_products };
+    let template = BundleDetailTemplate { ctx, bundle: updated_bundle, products: bundle_products };
     Ok(template)
 }
 
@@ -166,15 +177,16 @@ pub async fn delete_bundle(State(state): State<AppState>, Path(id): Path<i32>) -
 
 async fn product_detail(
     State(state): State<AppState>,
+    ctx: PageContext,
     Path(id): Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
     let product = state.product_service.get_product(id).await?;
-    let template = ProductDetailTemplate { product };
+    let template = ProductDetailTemplate { ctx, product };
     Ok(template.into_response())
 }
 
-async fn list_products(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+async fn list_products(State(state): State<AppState>, ctx: PageContext) -> Result<impl IntoResponse, AppError> {
     let products = state.product_service.get_all_products().await?;
-    let template = ProductListTemplate { products };
+    let template = ProductListTemplate { ctx, products };
     Ok(template.into_response())
 }
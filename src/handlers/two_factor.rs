@@ -0,0 +1,84 @@
+use crate::error::AppError;
+use crate::handlers::auth::is_htmx;
+use crate::middleware::{AuthUser, ClientIp, PageContext};
+use crate::models::TotpCodeRequest;
+use crate::routes::api_v1::AppState;
+use crate::services::TotpService;
+use crate::templates::{RecoveryCodesTemplate, TotpEnrollTemplate, TwoFactorTemplate};
+use askama_axum::IntoResponse;
+use askama_axum::Template;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::{Html, Response};
+use axum::{Form, Json};
+
+pub async fn show_two_factor(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    ctx: PageContext,
+) -> Result<impl IntoResponse, AppError> {
+    let enabled = state.totp_service.is_enabled(user.id).await?;
+    let template = TwoFactorTemplate { ctx, enabled };
+    Ok(Html(template.render().unwrap()))
+}
+
+pub async fn enroll(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let enrollment = state.totp_service.begin_enrollment(&user).await?;
+    if is_htmx(&headers) {
+        let template = TotpEnrollTemplate { enrollment };
+        return Ok(Html(template.render().unwrap()).into_response());
+    }
+    Ok(Json(enrollment).into_response())
+}
+
+pub async fn confirm(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    headers: HeaderMap,
+    Form(req): Form<TotpCodeRequest>,
+) -> Result<Response, AppError> {
+    let codes = state
+        .totp_service
+        .confirm_enrollment(user.id, &req.code)
+        .await?;
+    if is_htmx(&headers) {
+        let template = RecoveryCodesTemplate { codes };
+        return Ok(Html(template.render().unwrap()).into_response());
+    }
+    Ok(Json(codes).into_response())
+}
+
+pub async fn disable(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    ClientIp(client_ip): ClientIp,
+    Form(req): Form<TotpCodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    // A stolen session must not be able to guess its way past the second factor, so
+    // wrong codes count against the same limits as failed logins.
+    state
+        .login_throttle_service
+        .check(&user.username, client_ip)
+        .await?;
+    match state.totp_service.disable(user.id, &req.code).await {
+        Ok(()) => {
+            state
+                .login_throttle_service
+                .record_success(&user.username)
+                .await?
+        }
+        Err(AppError::BadRequest(message)) => {
+            state
+                .login_throttle_service
+                .record_failure(&user.username, client_ip)
+                .await?;
+            return Err(AppError::BadRequest(message));
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(Html("<p>Two-factor authentication has been disabled.</p>"))
+}
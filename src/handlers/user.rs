@@ -1,13 +1,49 @@
 use crate::error::AppError;
+use crate::middleware::PageContext;
+use crate::models::AssignRoleRequest;
 use crate::routes::api_v1::AppState;
 use crate::templates::UsersTemplate;
 use askama_axum::IntoResponse;
 use askama_axum::Template;
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::response::Html;
+use axum::Form;
 
-pub async fn get_users(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+pub async fn get_users(
+    State(state): State<AppState>,
+    ctx: PageContext,
+) -> Result<impl IntoResponse, AppError> {
     let users = state.user_service.get_all_users().await?;
-    let template = UsersTemplate { users };
+    let template = UsersTemplate { ctx, users };
     Ok(Html(template.render().unwrap()))
 }
+
+pub async fn assign_role(
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+    Form(req): Form<AssignRoleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    state.user_service.get_user_by_id(user_id).await?;
+    state
+        .permission_service
+        .assign_role(user_id, &req.role)
+        .await?;
+    Ok("Role assigned")
+}
+
+pub async fn remove_role(
+    State(state): State<AppState>,
+    Path((user_id, role)): Path<(i32, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    state.permission_service.remove_role(user_id, &role).await?;
+    Ok("Role removed")
+}
+
+pub async fn unlock_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = state.user_service.get_user_by_id(user_id).await?;
+    state.login_throttle_service.unlock(&user.username).await?;
+    Ok("User unlocked")
+}
@@ -0,0 +1,56 @@
+use crate::error::AppError;
+use crate::middleware::{session_cookie, AuthUser, ClientIp};
+use crate::models::{
+    FinishPasskeyLoginRequest, FinishPasskeyRegistrationRequest, WebauthnChallenge,
+};
+use crate::routes::api_v1::AppState;
+use crate::services::{AuthService, WebauthnService};
+use askama_axum::IntoResponse;
+use axum::extract::State;
+use axum::http::header;
+use axum::Json;
+use webauthn_rs::prelude::{CreationChallengeResponse, RequestChallengeResponse};
+
+pub async fn start_registration(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+) -> Result<Json<WebauthnChallenge<CreationChallengeResponse>>, AppError> {
+    let challenge = state.webauthn_service.start_registration(&user).await?;
+    Ok(Json(challenge))
+}
+
+pub async fn finish_registration(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Json(req): Json<FinishPasskeyRegistrationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    state
+        .webauthn_service
+        .finish_registration(user.id, &req.ceremony_id, &req.credential, req.name)
+        .await?;
+    Ok("Passkey registered successfully")
+}
+
+pub async fn start_login(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+) -> Result<Json<WebauthnChallenge<RequestChallengeResponse>>, AppError> {
+    let challenge = state.webauthn_service.start_login(client_ip).await?;
+    Ok(Json(challenge))
+}
+
+pub async fn finish_login(
+    State(state): State<AppState>,
+    Json(req): Json<FinishPasskeyLoginRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = state
+        .webauthn_service
+        .finish_login(&req.ceremony_id, &req.credential)
+        .await?;
+    let res = state.auth_service.login_with_passkey(user_id).await?;
+
+    Ok((
+        [(header::SET_COOKIE, session_cookie(&res.token))],
+        Json(res),
+    ))
+}
@@ -1,3 +1,4 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use repositories::ProductRepositoryImpl;
@@ -8,17 +9,31 @@ mod config;
 mod db;
 mod error;
 mod handlers;
+mod middleware;
 mod models;
 mod repositories;
 mod routes;
 mod services;
 mod templates;
+mod utils;
 
 use crate::config::AppConfig;
 use crate::db::create_pool;
-use crate::repositories::UserRepositoryImpl;
-use crate::routes::create_router;
-use crate::services::{AuthServiceImpl, OAuthServiceImpl, SiweServiceImpl, UserServiceImpl};
+use crate::repositories::{
+    ApiKeyRepositoryImpl, IdentityRepositoryImpl, LoginThrottleRepositoryImpl,
+    OAuth2RepositoryImpl, PasswordResetRepositoryImpl, PermissionRepositoryImpl,
+    RateLimitRepositoryImpl, RefreshTokenRepositoryImpl, RevocationRepositoryImpl,
+    SiweNonceRepositoryImpl, TotpRepositoryImpl, UserRepositoryImpl, WalletRepositoryImpl,
+    WebauthnCredentialRepositoryImpl,
+};
+use crate::routes::{create_router, AppState};
+use crate::services::{
+    ApiKeyServiceImpl, AuthServiceImpl, ContractWalletVerifierImpl, EmailVerificationServiceImpl,
+    FileMailer, JwtKeys, LoginThrottleServiceImpl, Mailer, OAuth2ServerServiceImpl,
+    OAuthServiceImpl, PasswordHasherImpl, PasswordPolicyImpl, PasswordResetServiceImpl,
+    PermissionServiceImpl, RateLimitServiceImpl, RevocationServiceImpl, SiweServiceImpl,
+    SmtpMailer, TotpServiceImpl, UserServiceImpl, WebauthnServiceImpl,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -28,33 +43,132 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let user_repository = Arc::new(UserRepositoryImpl::new(pool_arc.clone()));
     let product_repository = Arc::new(ProductRepositoryImpl::new(pool_arc.clone()));
+    let refresh_token_repository = Arc::new(RefreshTokenRepositoryImpl::new(pool_arc.clone()));
+    let revocation_repository = Arc::new(RevocationRepositoryImpl::new(pool_arc.clone()));
+    let identity_repository = Arc::new(IdentityRepositoryImpl::new(pool_arc.clone()));
+    let siwe_nonce_repository = Arc::new(SiweNonceRepositoryImpl::new(pool_arc.clone()));
+    let wallet_repository = Arc::new(WalletRepositoryImpl::new(pool_arc.clone()));
+    let permission_repository = Arc::new(PermissionRepositoryImpl::new(pool_arc.clone()));
+    let oauth2_repository = Arc::new(OAuth2RepositoryImpl::new(pool_arc.clone()));
 
     let user_service = Arc::new(UserServiceImpl::new(user_repository.clone()));
+    let revocation_service = Arc::new(RevocationServiceImpl::new(revocation_repository));
+    let permission_service = Arc::new(PermissionServiceImpl::new(permission_repository));
+    let mailer: Arc<dyn Mailer> = match &config.mail.smtp_url {
+        Some(smtp_url) => Arc::new(SmtpMailer::new(smtp_url, config.mail.from.clone())?),
+        None => Arc::new(FileMailer::new(&config.mail.outbox_dir)),
+    };
+    let rate_limit_service = Arc::new(RateLimitServiceImpl::new(Arc::new(
+        RateLimitRepositoryImpl::new(pool_arc.clone()),
+    )));
+    let email_verification_service = Arc::new(EmailVerificationServiceImpl::new(
+        user_repository.clone(),
+        mailer.clone(),
+        rate_limit_service.clone(),
+        config.jwt_secret.clone(),
+        config.app_base_url.clone(),
+    ));
+    let password_hasher = Arc::new(PasswordHasherImpl::new());
+    let password_policy = Arc::new(PasswordPolicyImpl::new(config.password_policy)?);
+    let password_reset_service = Arc::new(PasswordResetServiceImpl::new(
+        user_repository.clone(),
+        Arc::new(PasswordResetRepositoryImpl::new(pool_arc.clone())),
+        refresh_token_repository.clone(),
+        oauth2_repository.clone(),
+        revocation_service.clone(),
+        password_hasher.clone(),
+        password_policy.clone(),
+        mailer.clone(),
+        rate_limit_service.clone(),
+        config.app_base_url.clone(),
+    ));
+    let totp_service = Arc::new(TotpServiceImpl::new(
+        Arc::new(TotpRepositoryImpl::new(pool_arc.clone())),
+        config.totp_issuer.clone(),
+    ));
+    let login_throttle_service = Arc::new(LoginThrottleServiceImpl::new(Arc::new(
+        LoginThrottleRepositoryImpl::new(pool_arc.clone()),
+    )));
+    let jwt_keys = Arc::new(JwtKeys::new(
+        &config.jwt_keys,
+        &config.jwt_secret,
+        config.jwt_key_grace_period_seconds,
+    )?);
     let auth_service = Arc::new(AuthServiceImpl::new(
         user_repository.clone(),
+        refresh_token_repository,
+        oauth2_repository.clone(),
+        identity_repository,
+        wallet_repository,
+        revocation_service,
+        permission_service.clone(),
+        password_hasher,
+        password_policy,
+        email_verification_service.clone(),
+        totp_service.clone(),
+        login_throttle_service.clone(),
+        jwt_keys.clone(),
+        config.access_token.clone(),
         config.jwt_secret.clone(),
+        config.require_email_verification,
+    ));
+    let api_key_service = Arc::new(ApiKeyServiceImpl::new(
+        Arc::new(ApiKeyRepositoryImpl::new(pool_arc.clone())),
+        user_repository.clone(),
+        permission_service.clone(),
+    ));
+    let oauth2_server_service = Arc::new(OAuth2ServerServiceImpl::new(
+        oauth2_repository,
+        user_repository.clone(),
+        rate_limit_service.clone(),
+        &config.app_base_url,
     ));
     let oauth_service = Arc::new(OAuthServiceImpl::new(
-        config.oauth_client_id,
-        config.oauth_client_secret,
-        config.oauth_auth_url,
-        config.oauth_token_url,
-        config.oauth_redirect_url,
+        config.oauth_providers,
+        rate_limit_service.clone(),
+    )?);
+    let contract_wallet_verifier = Arc::new(ContractWalletVerifierImpl::new(config.siwe_rpc_urls)?);
+    let siwe_service = Arc::new(SiweServiceImpl::new(
+        siwe_nonce_repository,
+        contract_wallet_verifier,
+        rate_limit_service.clone(),
+        config.siwe_domain,
+        config.siwe_uri,
     ));
-    let siwe_service = Arc::new(SiweServiceImpl::new());
     let product_service = Arc::new(ProductServiceImpl::new(product_repository));
+    let webauthn_service = Arc::new(WebauthnServiceImpl::new(
+        Arc::new(WebauthnCredentialRepositoryImpl::new(pool_arc.clone())),
+        rate_limit_service,
+        &config.app_base_url,
+        &config.webauthn_rp_name,
+    )?);
 
-    let app = create_router(
+    let app = create_router(AppState {
         user_service,
         auth_service,
         oauth_service,
         siwe_service,
         product_service,
-    );
+        permission_service,
+        email_verification_service,
+        password_reset_service,
+        totp_service,
+        webauthn_service,
+        login_throttle_service,
+        jwt_keys,
+        api_key_service,
+        oauth2_server_service,
+        trusted_proxies: Arc::new(config.trusted_proxies),
+    });
 
     let listener = tokio::net::TcpListener::bind(&config.server_addr).await?;
     println!("Listening on {}", config.server_addr);
-    axum::serve(listener, app).await?;
+    // Login throttling needs the client address, or the proxy's when behind one.
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
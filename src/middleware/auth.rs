@@ -0,0 +1,128 @@
+use crate::error::AppError;
+use crate::models::User;
+use crate::routes::api_v1::AppState;
+use crate::services::{API_KEY_PREFIX, OAUTH2_ACCESS_TOKEN_PREFIX};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header, request::Parts, HeaderMap, HeaderName},
+    middleware::Next,
+    response::Response,
+};
+
+pub const AUTH_COOKIE: &str = "auth_token";
+pub const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+/// The authenticated caller, resolved from an API key, an OAuth2 access token, a bearer
+/// token or the auth cookie.
+#[derive(Clone)]
+pub struct AuthUser(pub User);
+
+/// Present when the caller authenticated with an API key or an OAuth2 access token, which
+/// may only use these permissions.
+#[derive(Clone)]
+pub struct DelegatedScopes(pub Vec<String>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        // `require_auth` may already have resolved the user for this request.
+        if let Some(auth_user) = parts.extensions.get::<AuthUser>() {
+            return Ok(auth_user.clone());
+        }
+
+        if let Some(key) = api_key_from_parts(parts) {
+            let (user, api_key) = state.api_key_service.authenticate(&key).await?;
+            parts.extensions.insert(DelegatedScopes(api_key.scopes));
+            return Ok(AuthUser(user));
+        }
+
+        let token = token_from_parts(parts).ok_or(AppError::Unauthorized)?;
+        if token.starts_with(OAUTH2_ACCESS_TOKEN_PREFIX) {
+            let (user, access_token) = state.oauth2_server_service.authenticate(&token).await?;
+            parts
+                .extensions
+                .insert(DelegatedScopes(access_token.scopes));
+            return Ok(AuthUser(user));
+        }
+        let user = state.auth_service.authenticate(&token).await?;
+
+        Ok(AuthUser(user))
+    }
+}
+
+/// The raw token presented by the caller, without any verification.
+pub struct AuthToken(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthToken {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        token_from_parts(parts)
+            .map(AuthToken)
+            .ok_or(AppError::Unauthorized)
+    }
+}
+
+/// Rejects the request with `AppError::Unauthorized` unless it carries a valid token.
+pub async fn require_auth(auth_user: AuthUser, mut req: Request, next: Next) -> Response {
+    req.extensions_mut().insert(auth_user);
+    next.run(req).await
+}
+
+/// Rejects requests authenticated with an API key or an OAuth2 access token, for routes
+/// that manage the account itself.
+///
+/// Must run after `require_auth`.
+pub async fn require_session(req: Request, next: Next) -> Result<Response, AppError> {
+    if req.extensions().get::<DelegatedScopes>().is_some() {
+        return Err(AppError::Forbidden);
+    }
+    Ok(next.run(req).await)
+}
+
+/// `Set-Cookie` value that stores the access token for browser sessions.
+pub fn session_cookie(token: &str) -> String {
+    format!("{AUTH_COOKIE}={token}; Path=/; HttpOnly; Secure; SameSite=Lax")
+}
+
+/// `Set-Cookie` value that removes the browser session cookie.
+pub fn clear_session_cookie() -> String {
+    format!("{AUTH_COOKIE}=; Path=/; HttpOnly; Secure; SameSite=Lax; Max-Age=0")
+}
+
+fn api_key_from_parts(parts: &Parts) -> Option<String> {
+    if let Some(value) = parts.headers.get(API_KEY_HEADER) {
+        return value.to_str().ok().map(|key| key.trim().to_string());
+    }
+
+    token_from_parts(parts).filter(|token| token.starts_with(API_KEY_PREFIX))
+}
+
+fn token_from_parts(parts: &Parts) -> Option<String> {
+    if let Some(value) = parts.headers.get(header::AUTHORIZATION) {
+        let value = value.to_str().ok()?;
+        return value
+            .strip_prefix("Bearer ")
+            .map(|token| token.trim().to_string());
+    }
+
+    cookie_value(&parts.headers, AUTH_COOKIE)
+}
+
+pub fn cookie_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
@@ -0,0 +1,60 @@
+use crate::error::AppError;
+use crate::middleware::auth::{cookie_value, API_KEY_HEADER};
+use crate::utils::crypto::{constant_time_eq, random_token};
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+
+pub const CSRF_COOKIE: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// The double-submit token for the current browser, exposed to templates.
+#[derive(Clone)]
+pub struct CsrfToken(pub String);
+
+/// Double-submit cookie protection for state-changing requests.
+///
+/// Requests authenticated with an `Authorization` or `X-API-Key` header carry no
+/// ambient credentials and are not checked.
+pub async fn csrf_protect(mut req: Request, next: Next) -> Result<Response, AppError> {
+    let cookie_token = cookie_value(req.headers(), CSRF_COOKIE);
+
+    let has_credential_header = req.headers().contains_key(header::AUTHORIZATION)
+        || req.headers().contains_key(API_KEY_HEADER);
+
+    if is_state_changing(req.method()) && !has_credential_header {
+        let header_token = req
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        match (cookie_token.as_deref(), header_token) {
+            (Some(expected), Some(actual)) if constant_time_eq(expected, actual) => {}
+            _ => return Err(AppError::CsrfTokenMismatch),
+        }
+    }
+
+    let (token, is_new) = match cookie_token {
+        Some(token) => (token, false),
+        None => (random_token(32), true),
+    };
+    req.extensions_mut().insert(CsrfToken(token.clone()));
+
+    let mut response = next.run(req).await;
+    if is_new {
+        let cookie = format!("{CSRF_COOKIE}={token}; Path=/; HttpOnly; Secure; SameSite=Lax");
+        response.headers_mut().append(
+            header::SET_COOKIE,
+            HeaderValue::from_str(&cookie).map_err(|_| AppError::InternalServerError)?,
+        );
+    }
+
+    Ok(response)
+}
+
+fn is_state_changing(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
@@ -0,0 +1,14 @@
+pub mod auth;
+pub mod client_ip;
+pub mod csrf;
+pub mod page_context;
+pub mod permission;
+
+pub use auth::{
+    clear_session_cookie, require_auth, require_session, session_cookie, AuthToken, AuthUser,
+    DelegatedScopes,
+};
+pub use client_ip::ClientIp;
+pub use csrf::{csrf_protect, CsrfToken};
+pub use page_context::PageContext;
+pub use permission::require_permission;
@@ -0,0 +1,37 @@
+use crate::middleware::{AuthUser, CsrfToken};
+use crate::models::User;
+use crate::routes::api_v1::AppState;
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use std::convert::Infallible;
+
+/// Per-request data shared by every page that extends `base.html`.
+#[derive(Clone, Default)]
+pub struct PageContext {
+    pub current_user: Option<User>,
+    pub csrf_token: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for PageContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let current_user = AuthUser::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|AuthUser(user)| user);
+        let csrf_token = parts
+            .extensions
+            .get::<CsrfToken>()
+            .map(|CsrfToken(token)| token.clone())
+            .unwrap_or_default();
+
+        Ok(PageContext {
+            current_user,
+            csrf_token,
+        })
+    }
+}
@@ -0,0 +1,42 @@
+use crate::error::AppError;
+use crate::middleware::{AuthUser, DelegatedScopes};
+use crate::routes::api_v1::AppState;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::future::Future;
+use std::pin::Pin;
+
+type GuardFuture = Pin<Box<dyn Future<Output = Result<Response, AppError>> + Send>>;
+
+/// Builds a middleware for `from_fn_with_state` that rejects callers lacking `permission`.
+///
+/// Unauthenticated requests are rejected with `Unauthorized`, authenticated ones
+/// without the permission with `Forbidden`. Requests made with an API key or an
+/// OAuth2 access token also need the permission among its scopes.
+pub fn require_permission(
+    permission: &'static str,
+) -> impl Fn(State<AppState>, AuthUser, Request, Next) -> GuardFuture + Clone + Send + Sync + 'static
+{
+    move |State(state): State<AppState>, auth_user: AuthUser, mut req: Request, next: Next| {
+        Box::pin(async move {
+            if let Some(DelegatedScopes(scopes)) = req.extensions().get::<DelegatedScopes>() {
+                if !scopes.iter().any(|scope| scope == permission) {
+                    return Err(AppError::Forbidden);
+                }
+            }
+            if !state
+                .permission_service
+                .has_permission(auth_user.0.id, permission)
+                .await?
+            {
+                return Err(AppError::Forbidden);
+            }
+
+            req.extensions_mut().insert(auth_user);
+            Ok(next.run(req).await)
+        })
+    }
+}
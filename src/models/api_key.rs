@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct ApiKey {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<OffsetDateTime>,
+    pub last_used_at: Option<OffsetDateTime>,
+    pub revoked_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+/// A freshly created key. The plaintext `key` is never stored and cannot be shown again.
+#[derive(Serialize)]
+pub struct NewApiKey {
+    pub key: String,
+    pub api_key: ApiKey,
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Space-separated permissions, e.g. `products:write users:read`.
+    #[serde(default)]
+    pub scopes: String,
+    /// `0` creates a key that never expires.
+    #[serde(default)]
+    pub expires_in_days: u32,
+}
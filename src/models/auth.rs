@@ -11,9 +11,47 @@ pub struct RegisterRequest {
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+    /// Local path to return to after signing in.
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
 }
 
 #[derive(Serialize)]
 pub struct AuthResponse {
     pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+/// Returned instead of tokens when the password was right but a second factor is still needed.
+#[derive(Serialize)]
+pub struct MfaChallenge {
+    pub mfa_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Authenticated(AuthResponse),
+    MfaRequired(MfaChallenge),
 }
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Claims carried by access tokens, which downstream services verify against our JWKS.
+#[derive(Serialize, Deserialize)]
+pub struct Claims {
+    pub iss: String,
+    /// The user ID, sent as a string since RFC 7519 makes `sub` a StringOrURI.
+    #[serde(serialize_with = "serialize_sub", deserialize_with = "deserialize_sub")]
+    pub sub: i32,
+    pub aud: String,
+    pub exp: u64,
+    pub nbf: u64,
+    pub iat: u64,
+    pub jti: String,
+    /// Session the token belongs to, shared by every token refreshed from the same login.
+    pub sid: String,
+    pub username: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+fn serialize_sub<S: Serializer>(sub: &i32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(sub)
+}
+
+fn deserialize_sub<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+    // Tokens issued before `sub` became a string carry it as a number until they expire.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Sub {
+        String(String),
+        Number(i32),
+    }
+
+    match Sub::deserialize(deserializer)? {
+        Sub::String(sub) => sub.parse().map_err(serde::de::Error::custom),
+        Sub::Number(sub) => Ok(sub),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn claims(sub: serde_json::Value) -> serde_json::Value {
+        json!({
+            "iss": "https://auth.example.com",
+            "sub": sub,
+            "aud": "api",
+            "exp": 2,
+            "nbf": 1,
+            "iat": 1,
+            "jti": "jti",
+            "sid": "sid",
+            "username": "alice",
+        })
+    }
+
+    #[test]
+    fn sub_round_trips_as_a_string() {
+        let decoded: Claims = serde_json::from_value(claims(json!("42"))).unwrap();
+
+        assert_eq!(decoded.sub, 42);
+        assert_eq!(serde_json::to_value(&decoded).unwrap()["sub"], json!("42"));
+    }
+
+    #[test]
+    fn accepts_numeric_sub_from_older_tokens() {
+        let decoded: Claims = serde_json::from_value(claims(json!(42))).unwrap();
+
+        assert_eq!(decoded.sub, 42);
+    }
+
+    #[test]
+    fn rejects_non_numeric_sub() {
+        assert!(serde_json::from_value::<Claims>(claims(json!("alice"))).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct UserIdentity {
+    pub id: i32,
+    pub provider: String,
+    pub subject: String,
+    pub user_id: i32,
+}
+
+/// The account details an external identity provider vouches for.
+#[derive(Clone, Debug)]
+pub struct ExternalIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub username: Option<String>,
+}
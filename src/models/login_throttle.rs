@@ -0,0 +1,10 @@
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct LoginThrottle {
+    pub key: String,
+    pub failures: i32,
+    pub last_failure_at: OffsetDateTime,
+    pub locked_until: Option<OffsetDateTime>,
+}
@@ -1,7 +1,50 @@
+pub mod api_key;
 pub mod auth;
+pub mod claims;
+pub mod identity;
+pub mod login_throttle;
+pub mod oauth2;
+pub mod password_reset;
 pub mod product;
+pub mod rate_limit;
+pub mod refresh_token;
+pub mod revocation;
+pub mod role;
+pub mod totp;
 pub mod user;
+pub mod validation;
+pub mod wallet;
+pub mod webauthn;
 
-pub use auth::{AuthResponse, LoginRequest, RegisterRequest};
+pub use api_key::{ApiKey, CreateApiKeyRequest, NewApiKey};
+pub use auth::{
+    AuthResponse, LoginQuery, LoginRequest, LoginResult, MfaChallenge, RefreshRequest,
+    RegisterRequest, ResendVerificationRequest, VerifyEmailQuery,
+};
+pub use claims::Claims;
+pub use identity::{ExternalIdentity, UserIdentity};
+pub use login_throttle::LoginThrottle;
+pub use oauth2::{
+    AuthorizationError, AuthorizationRequest, AuthorizeQuery, ClientCredentials, ConsentRequest,
+    CreateOAuth2ClientRequest, DeviceAuthorizationRequest, DeviceAuthorizationResponse,
+    DeviceQuery, DeviceVerificationRequest, IntrospectionResponse, NewOAuth2Client,
+    OAuth2AuthorizationCode, OAuth2Client, OAuth2DeviceCode, OAuth2Token,
+    PendingDeviceAuthorization, TokenOperationRequest, TokenRequest, TokenResponse,
+    UserInfoResponse,
+};
+pub use password_reset::{
+    ForgotPasswordRequest, PasswordResetToken, ResetPasswordQuery, ResetPasswordRequest,
+};
 pub use product::{BundleProduct, Product, ProductBundle};
+pub use rate_limit::RateLimit;
+pub use refresh_token::RefreshToken;
+pub use revocation::{RevokedToken, UserTokenRevocation};
+pub use role::AssignRoleRequest;
+pub use totp::{MfaLoginRequest, TotpCodeRequest, TotpEnrollment, UserTotp};
 pub use user::User;
+pub use validation::FieldError;
+pub use wallet::WalletAddress;
+pub use webauthn::{
+    FinishPasskeyLoginRequest, FinishPasskeyRegistrationRequest, WebauthnChallenge,
+    WebauthnCredential,
+};
@@ -0,0 +1,237 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+pub const ACCESS_TOKEN_TYPE: &str = "access";
+pub const REFRESH_TOKEN_TYPE: &str = "refresh";
+
+pub const DEVICE_CODE_PENDING: &str = "pending";
+pub const DEVICE_CODE_APPROVED: &str = "approved";
+pub const DEVICE_CODE_DENIED: &str = "denied";
+
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct OAuth2Client {
+    pub id: i32,
+    pub client_id: String,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub scopes: Vec<String>,
+    pub created_at: OffsetDateTime,
+}
+
+impl OAuth2Client {
+    /// Confidential clients authenticate with a secret; public ones must use PKCE instead.
+    pub fn is_confidential(&self) -> bool {
+        self.client_secret_hash.is_some()
+    }
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct OAuth2AuthorizationCode {
+    pub id: i32,
+    pub code_hash: String,
+    pub client_id: String,
+    pub user_id: i32,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    /// S256 PKCE challenge.
+    pub code_challenge: Option<String>,
+    pub grant_id: String,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct OAuth2Token {
+    pub id: i32,
+    pub token_hash: String,
+    pub token_type: String,
+    pub client_id: String,
+    pub user_id: Option<i32>,
+    pub scopes: Vec<String>,
+    pub grant_id: String,
+    pub expires_at: OffsetDateTime,
+    pub revoked_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct OAuth2DeviceCode {
+    pub id: i32,
+    pub device_code_hash: String,
+    pub user_code: String,
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    pub status: String,
+    pub user_id: Option<i32>,
+    pub interval_seconds: i32,
+    pub last_polled_at: Option<OffsetDateTime>,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateOAuth2ClientRequest {
+    pub name: String,
+    /// Space-separated.
+    pub redirect_uris: String,
+    /// Space-separated scopes the client may request.
+    #[serde(default)]
+    pub scopes: String,
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+/// A freshly registered client. The plaintext secret is never stored and cannot be shown again.
+#[derive(Serialize)]
+pub struct NewOAuth2Client {
+    pub client: OAuth2Client,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+/// Parameters of an authorization request, echoed back by the consent form.
+#[derive(Clone, Deserialize)]
+pub struct AuthorizeQuery {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub query: AuthorizeQuery,
+    /// `allow` or `deny`.
+    pub decision: String,
+}
+
+/// A validated authorization request, ready to show on the consent page.
+pub struct AuthorizationRequest {
+    pub client: OAuth2Client,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub device_code: Option<String>,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub scope: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i32,
+}
+
+/// A device authorization request waiting for the user to approve or deny it.
+pub struct PendingDeviceAuthorization {
+    pub client: OAuth2Client,
+    /// Formatted for display, e.g. `BCDF-GHJK`.
+    pub user_code: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeviceQuery {
+    pub user_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeviceVerificationRequest {
+    pub user_code: String,
+    /// `allow` or `deny`; absent while the user is still confirming the code.
+    pub decision: Option<String>,
+}
+
+/// Body of RFC 7662 introspection and RFC 7009 revocation requests. Any
+/// `token_type_hint` is ignored since both token types are looked up the same way.
+#[derive(Deserialize)]
+pub struct TokenOperationRequest {
+    pub token: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+}
+
+/// Who granted an access token, in the shape of an OpenID Connect userinfo response.
+#[derive(Serialize)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    pub preferred_username: String,
+    /// Only disclosed to clients granted the `email` scope.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+/// Credentials a client presented, from HTTP Basic auth or the request body.
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+/// Why an authorization request cannot go ahead.
+pub enum AuthorizationError {
+    /// The client or redirect URI cannot be trusted, so the user must not be sent back.
+    Invalid(AppError),
+    /// The client should be told through this redirect URL.
+    Redirect(String),
+}
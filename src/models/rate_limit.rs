@@ -0,0 +1,9 @@
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RateLimit {
+    pub key: String,
+    pub hits: i32,
+    pub window_started_at: OffsetDateTime,
+}
@@ -0,0 +1,13 @@
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub family_id: String,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+    pub revoked_at: Option<OffsetDateTime>,
+}
@@ -0,0 +1,15 @@
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RevokedToken {
+    pub jti: String,
+    pub user_id: i32,
+    pub expires_at: OffsetDateTime,
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct UserTokenRevocation {
+    pub user_id: i32,
+    pub revoked_before: OffsetDateTime,
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const ADMIN_ROLE: &str = "admin";
+pub const DEFAULT_ROLE: &str = "user";
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct Role {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct AssignRoleRequest {
+    pub role: String,
+}
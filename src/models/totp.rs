@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct UserTotp {
+    pub user_id: i32,
+    /// Base32-encoded shared secret.
+    pub secret: String,
+    pub confirmed_at: Option<OffsetDateTime>,
+    pub last_used_step: i64,
+}
+
+/// What the user needs to add the account to an authenticator app.
+#[derive(Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_url: String,
+    /// PNG of `otpauth_url`, base64-encoded.
+    pub qr_code: String,
+}
+
+#[derive(Deserialize)]
+pub struct TotpCodeRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct MfaLoginRequest {
+    pub mfa_token: String,
+    pub code: String,
+    #[serde(default)]
+    pub next: Option<String>,
+}
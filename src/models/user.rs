@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
 
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: i32,
     pub username: String,
-    pub email: String,
-    pub password_hash: String,
+    pub email: Option<String>,
+    pub password_hash: Option<String>,
+    pub email_verified_at: Option<OffsetDateTime>,
 }
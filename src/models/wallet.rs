@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Clone, Debug, Serialize, Deserialize, FromRow)]
+pub struct WalletAddress {
+    pub id: i32,
+    pub user_id: i32,
+    /// Lowercase, `0x`-prefixed hex address.
+    pub address: String,
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use webauthn_rs::prelude::{Passkey, PublicKeyCredential, RegisterPublicKeyCredential};
+
+#[derive(Clone, Debug, FromRow)]
+pub struct WebauthnCredential {
+    pub id: i32,
+    pub user_id: i32,
+    pub user_handle: Uuid,
+    /// Hex-encoded raw credential ID.
+    pub credential_id: String,
+    pub passkey: Json<Passkey>,
+    pub sign_count: i64,
+    pub name: String,
+    pub created_at: OffsetDateTime,
+    pub last_used_at: Option<OffsetDateTime>,
+}
+
+/// Options to pass to `navigator.credentials`, plus the ID of the ceremony to finish.
+#[derive(Serialize)]
+pub struct WebauthnChallenge<T> {
+    pub ceremony_id: String,
+    pub options: T,
+}
+
+#[derive(Deserialize)]
+pub struct FinishPasskeyRegistrationRequest {
+    pub ceremony_id: String,
+    pub credential: RegisterPublicKeyCredential,
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FinishPasskeyLoginRequest {
+    pub ceremony_id: String,
+    pub credential: PublicKeyCredential,
+}
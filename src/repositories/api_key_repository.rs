@@ -0,0 +1,128 @@
+use crate::error::AppError;
+use crate::models::ApiKey;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create_key(
+        &self,
+        user_id: i32,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[String],
+        expires_at: Option<OffsetDateTime>,
+    ) -> Result<ApiKey, AppError>;
+    async fn get_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, AppError>;
+    /// Keys of the user that have not been revoked, newest first.
+    async fn get_keys_for_user(&self, user_id: i32) -> Result<Vec<ApiKey>, AppError>;
+    async fn record_use(&self, id: i32) -> Result<(), AppError>;
+    /// Revokes the key if it belongs to `user_id`, returning `false` otherwise.
+    async fn revoke_key(&self, id: i32, user_id: i32) -> Result<bool, AppError>;
+}
+
+pub struct ApiKeyRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl ApiKeyRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for ApiKeyRepositoryImpl {
+    async fn create_key(
+        &self,
+        user_id: i32,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[String],
+        expires_at: Option<OffsetDateTime>,
+    ) -> Result<ApiKey, AppError> {
+        let api_key = sqlx::query_as!(
+            ApiKey,
+            r#"INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, name, prefix, key_hash, scopes, expires_at, last_used_at,
+                revoked_at, created_at"#,
+            user_id,
+            name,
+            prefix,
+            key_hash,
+            scopes,
+            expires_at
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(api_key)
+    }
+
+    async fn get_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, AppError> {
+        let api_key = sqlx::query_as!(
+            ApiKey,
+            r#"SELECT id, user_id, name, prefix, key_hash, scopes, expires_at, last_used_at,
+                revoked_at, created_at
+            FROM api_keys WHERE key_hash = $1"#,
+            key_hash
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(api_key)
+    }
+
+    async fn get_keys_for_user(&self, user_id: i32) -> Result<Vec<ApiKey>, AppError> {
+        let api_keys = sqlx::query_as!(
+            ApiKey,
+            r#"SELECT id, user_id, name, prefix, key_hash, scopes, expires_at, last_used_at,
+                revoked_at, created_at
+            FROM api_keys WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at DESC"#,
+            user_id
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(api_keys)
+    }
+
+    async fn record_use(&self, id: i32) -> Result<(), AppError> {
+        // Scripts may call in a tight loop; a minute's precision is plenty.
+        sqlx::query!(
+            r#"UPDATE api_keys SET last_used_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+                AND (last_used_at IS NULL OR last_used_at < CURRENT_TIMESTAMP - INTERVAL '1 minute')"#,
+            id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn revoke_key(&self, id: i32, user_id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"UPDATE api_keys SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL"#,
+            id,
+            user_id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,66 @@
+use crate::error::AppError;
+use crate::models::UserIdentity;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait IdentityRepository: Send + Sync {
+    async fn get_identity(&self, provider: &str, subject: &str) -> Result<UserIdentity, AppError>;
+    async fn create_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        user_id: i32,
+    ) -> Result<UserIdentity, AppError>;
+}
+
+pub struct IdentityRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl IdentityRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for IdentityRepositoryImpl {
+    async fn get_identity(&self, provider: &str, subject: &str) -> Result<UserIdentity, AppError> {
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            "SELECT id, provider, subject, user_id FROM user_identities WHERE provider = $1 AND subject = $2",
+            provider,
+            subject
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(identity)
+    }
+
+    async fn create_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        user_id: i32,
+    ) -> Result<UserIdentity, AppError> {
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            r#"INSERT INTO user_identities (provider, subject, user_id)
+            VALUES ($1, $2, $3)
+            RETURNING id, provider, subject, user_id"#,
+            provider,
+            subject,
+            user_id
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(identity)
+    }
+}
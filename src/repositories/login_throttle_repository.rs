@@ -0,0 +1,97 @@
+use crate::error::AppError;
+use crate::models::LoginThrottle;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+#[async_trait]
+pub trait LoginThrottleRepository: Send + Sync {
+    /// Returns the latest lock expiry among `keys` that is still in the future.
+    async fn get_locked_until(&self, keys: &[String]) -> Result<Option<OffsetDateTime>, AppError>;
+    /// Counts a failure for `key`, starting over if the last one is older than
+    /// `reset_after_seconds`, and returns the updated counter.
+    async fn record_failure(
+        &self,
+        key: &str,
+        reset_after_seconds: i64,
+    ) -> Result<LoginThrottle, AppError>;
+    async fn lock(&self, key: &str, locked_until: OffsetDateTime) -> Result<(), AppError>;
+    async fn clear(&self, key: &str) -> Result<(), AppError>;
+}
+
+pub struct LoginThrottleRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl LoginThrottleRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl LoginThrottleRepository for LoginThrottleRepositoryImpl {
+    async fn get_locked_until(&self, keys: &[String]) -> Result<Option<OffsetDateTime>, AppError> {
+        let row = sqlx::query!(
+            r#"SELECT MAX(locked_until) as locked_until
+            FROM login_throttles
+            WHERE key = ANY($1) AND locked_until > NOW()"#,
+            keys
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(row.locked_until)
+    }
+
+    async fn record_failure(
+        &self,
+        key: &str,
+        reset_after_seconds: i64,
+    ) -> Result<LoginThrottle, AppError> {
+        let throttle = sqlx::query_as!(
+            LoginThrottle,
+            r#"INSERT INTO login_throttles (key, failures, last_failure_at)
+            VALUES ($1, 1, NOW())
+            ON CONFLICT (key) DO UPDATE SET
+                failures = CASE
+                    WHEN login_throttles.last_failure_at < NOW() - make_interval(secs => $2)
+                    THEN 1
+                    ELSE login_throttles.failures + 1
+                END,
+                last_failure_at = NOW()
+            RETURNING key, failures, last_failure_at, locked_until"#,
+            key,
+            reset_after_seconds as f64
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(throttle)
+    }
+
+    async fn lock(&self, key: &str, locked_until: OffsetDateTime) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE login_throttles SET locked_until = $2 WHERE key = $1",
+            key,
+            locked_until
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn clear(&self, key: &str) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM login_throttles WHERE key = $1", key)
+            .execute(&*self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
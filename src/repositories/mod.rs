@@ -1,5 +1,33 @@
+pub mod api_key_repository;
+pub mod identity_repository;
+pub mod login_throttle_repository;
+pub mod oauth2_repository;
+pub mod password_reset_repository;
+pub mod permission_repository;
 pub mod product_repository;
+pub mod rate_limit_repository;
+pub mod refresh_token_repository;
+pub mod revocation_repository;
+pub mod siwe_nonce_repository;
+pub mod totp_repository;
 pub mod user_repository;
+pub mod wallet_repository;
+pub mod webauthn_credential_repository;
 
+pub use api_key_repository::{ApiKeyRepository, ApiKeyRepositoryImpl};
+pub use identity_repository::{IdentityRepository, IdentityRepositoryImpl};
+pub use login_throttle_repository::{LoginThrottleRepository, LoginThrottleRepositoryImpl};
+pub use oauth2_repository::{OAuth2Repository, OAuth2RepositoryImpl};
+pub use password_reset_repository::{PasswordResetRepository, PasswordResetRepositoryImpl};
+pub use permission_repository::{PermissionRepository, PermissionRepositoryImpl};
 pub use product_repository::{ProductRepository, ProductRepositoryImpl};
+pub use rate_limit_repository::{RateLimitRepository, RateLimitRepositoryImpl};
+pub use refresh_token_repository::{RefreshTokenRepository, RefreshTokenRepositoryImpl};
+pub use revocation_repository::{RevocationRepository, RevocationRepositoryImpl};
+pub use siwe_nonce_repository::{SiweNonceRepository, SiweNonceRepositoryImpl};
+pub use totp_repository::{TotpRepository, TotpRepositoryImpl};
 pub use user_repository::{UserRepository, UserRepositoryImpl};
+pub use wallet_repository::{WalletRepository, WalletRepositoryImpl};
+pub use webauthn_credential_repository::{
+    WebauthnCredentialRepository, WebauthnCredentialRepositoryImpl,
+};
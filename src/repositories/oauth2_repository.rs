@@ -0,0 +1,438 @@
+use crate::error::AppError;
+use crate::models::oauth2::DEVICE_CODE_PENDING;
+use crate::models::{OAuth2AuthorizationCode, OAuth2Client, OAuth2DeviceCode, OAuth2Token};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+#[async_trait]
+pub trait OAuth2Repository: Send + Sync {
+    async fn create_client(
+        &self,
+        client_id: &str,
+        client_secret_hash: Option<&str>,
+        name: &str,
+        redirect_uris: &[String],
+        scopes: &[String],
+    ) -> Result<OAuth2Client, AppError>;
+    async fn get_clients(&self) -> Result<Vec<OAuth2Client>, AppError>;
+    async fn get_client(&self, client_id: &str) -> Result<OAuth2Client, AppError>;
+    /// Deletes the client along with its codes and tokens, returning `false` if it did not exist.
+    async fn delete_client(&self, client_id: &str) -> Result<bool, AppError>;
+    #[allow(clippy::too_many_arguments)]
+    async fn create_code(
+        &self,
+        code_hash: &str,
+        client_id: &str,
+        user_id: i32,
+        redirect_uri: &str,
+        scopes: &[String],
+        code_challenge: Option<&str>,
+        grant_id: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError>;
+    async fn get_code_by_hash(&self, code_hash: &str) -> Result<OAuth2AuthorizationCode, AppError>;
+    /// Marks the code as used, returning `false` if it already was.
+    async fn mark_code_used(&self, id: i32) -> Result<bool, AppError>;
+    #[allow(clippy::too_many_arguments)]
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        token_type: &str,
+        client_id: &str,
+        user_id: Option<i32>,
+        scopes: &[String],
+        grant_id: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError>;
+    async fn get_token_by_hash(&self, token_hash: &str) -> Result<OAuth2Token, AppError>;
+    /// Revokes the token, returning `false` if it was already revoked.
+    async fn revoke_token(&self, id: i32) -> Result<bool, AppError>;
+    /// Revokes every token issued from the same authorization.
+    async fn revoke_grant(&self, grant_id: &str) -> Result<(), AppError>;
+    /// Revokes every token the user has granted, along with codes not yet exchanged for one.
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError>;
+    /// Returns `false` if another pending request already uses `user_code`.
+    async fn create_device_code(
+        &self,
+        device_code_hash: &str,
+        user_code: &str,
+        client_id: &str,
+        scopes: &[String],
+        interval_seconds: i32,
+        expires_at: OffsetDateTime,
+    ) -> Result<bool, AppError>;
+    async fn get_device_code_by_hash(
+        &self,
+        device_code_hash: &str,
+    ) -> Result<OAuth2DeviceCode, AppError>;
+    /// The unexpired request still waiting for a decision under `user_code`.
+    async fn get_pending_device_code(&self, user_code: &str) -> Result<OAuth2DeviceCode, AppError>;
+    /// Records the user's decision, returning `false` if the request was no longer pending.
+    async fn decide_device_code(
+        &self,
+        id: i32,
+        user_id: i32,
+        status: &str,
+    ) -> Result<bool, AppError>;
+    async fn record_device_poll(&self, id: i32, interval_seconds: i32) -> Result<(), AppError>;
+    /// Marks the device code as exchanged, returning `false` if it already was.
+    async fn mark_device_code_used(&self, id: i32) -> Result<bool, AppError>;
+}
+
+pub struct OAuth2RepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl OAuth2RepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OAuth2Repository for OAuth2RepositoryImpl {
+    async fn create_client(
+        &self,
+        client_id: &str,
+        client_secret_hash: Option<&str>,
+        name: &str,
+        redirect_uris: &[String],
+        scopes: &[String],
+    ) -> Result<OAuth2Client, AppError> {
+        let client = sqlx::query_as!(
+            OAuth2Client,
+            r#"INSERT INTO oauth2_clients (client_id, client_secret_hash, name, redirect_uris, scopes)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, client_id, client_secret_hash, name, redirect_uris, scopes, created_at"#,
+            client_id,
+            client_secret_hash,
+            name,
+            redirect_uris,
+            scopes
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(client)
+    }
+
+    async fn get_clients(&self) -> Result<Vec<OAuth2Client>, AppError> {
+        let clients = sqlx::query_as!(
+            OAuth2Client,
+            r#"SELECT id, client_id, client_secret_hash, name, redirect_uris, scopes, created_at
+            FROM oauth2_clients ORDER BY created_at"#
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(clients)
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuth2Client, AppError> {
+        let client = sqlx::query_as!(
+            OAuth2Client,
+            r#"SELECT id, client_id, client_secret_hash, name, redirect_uris, scopes, created_at
+            FROM oauth2_clients WHERE client_id = $1"#,
+            client_id
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(client)
+    }
+
+    async fn delete_client(&self, client_id: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!("DELETE FROM oauth2_clients WHERE client_id = $1", client_id)
+            .execute(&*self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn create_code(
+        &self,
+        code_hash: &str,
+        client_id: &str,
+        user_id: i32,
+        redirect_uri: &str,
+        scopes: &[String],
+        code_challenge: Option<&str>,
+        grant_id: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"INSERT INTO oauth2_authorization_codes
+                (code_hash, client_id, user_id, redirect_uri, scopes, code_challenge, grant_id, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+            code_hash,
+            client_id,
+            user_id,
+            redirect_uri,
+            scopes,
+            code_challenge,
+            grant_id,
+            expires_at
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn get_code_by_hash(&self, code_hash: &str) -> Result<OAuth2AuthorizationCode, AppError> {
+        let code = sqlx::query_as!(
+            OAuth2AuthorizationCode,
+            r#"SELECT id, code_hash, client_id, user_id, redirect_uri, scopes, code_challenge,
+                grant_id, expires_at, used_at
+            FROM oauth2_authorization_codes WHERE code_hash = $1"#,
+            code_hash
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(code)
+    }
+
+    async fn mark_code_used(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"UPDATE oauth2_authorization_codes SET used_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND used_at IS NULL"#,
+            id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn create_token(
+        &self,
+        token_hash: &str,
+        token_type: &str,
+        client_id: &str,
+        user_id: Option<i32>,
+        scopes: &[String],
+        grant_id: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"INSERT INTO oauth2_tokens
+                (token_hash, token_type, client_id, user_id, scopes, grant_id, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+            token_hash,
+            token_type,
+            client_id,
+            user_id,
+            scopes,
+            grant_id,
+            expires_at
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn get_token_by_hash(&self, token_hash: &str) -> Result<OAuth2Token, AppError> {
+        let token = sqlx::query_as!(
+            OAuth2Token,
+            r#"SELECT id, token_hash, token_type, client_id, user_id, scopes, grant_id, expires_at,
+                revoked_at, created_at
+            FROM oauth2_tokens WHERE token_hash = $1"#,
+            token_hash
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(token)
+    }
+
+    async fn revoke_token(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"UPDATE oauth2_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND revoked_at IS NULL"#,
+            id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn revoke_grant(&self, grant_id: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"UPDATE oauth2_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE grant_id = $1 AND revoked_at IS NULL"#,
+            grant_id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            r#"UPDATE oauth2_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND revoked_at IS NULL"#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            r#"UPDATE oauth2_authorization_codes SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND used_at IS NULL"#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            r#"UPDATE oauth2_device_codes SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND used_at IS NULL"#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn create_device_code(
+        &self,
+        device_code_hash: &str,
+        user_code: &str,
+        client_id: &str,
+        scopes: &[String],
+        interval_seconds: i32,
+        expires_at: OffsetDateTime,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"INSERT INTO oauth2_device_codes
+                (device_code_hash, user_code, client_id, scopes, interval_seconds, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_code) WHERE status = 'pending' DO NOTHING"#,
+            device_code_hash,
+            user_code,
+            client_id,
+            scopes,
+            interval_seconds,
+            expires_at
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn get_device_code_by_hash(
+        &self,
+        device_code_hash: &str,
+    ) -> Result<OAuth2DeviceCode, AppError> {
+        let device_code = sqlx::query_as!(
+            OAuth2DeviceCode,
+            r#"SELECT id, device_code_hash, user_code, client_id, scopes, status, user_id,
+                interval_seconds, last_polled_at, expires_at, used_at
+            FROM oauth2_device_codes WHERE device_code_hash = $1"#,
+            device_code_hash
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(device_code)
+    }
+
+    async fn get_pending_device_code(&self, user_code: &str) -> Result<OAuth2DeviceCode, AppError> {
+        let device_code = sqlx::query_as!(
+            OAuth2DeviceCode,
+            r#"SELECT id, device_code_hash, user_code, client_id, scopes, status, user_id,
+                interval_seconds, last_polled_at, expires_at, used_at
+            FROM oauth2_device_codes
+            WHERE user_code = $1 AND status = $2 AND expires_at > CURRENT_TIMESTAMP"#,
+            user_code,
+            DEVICE_CODE_PENDING
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(device_code)
+    }
+
+    async fn decide_device_code(
+        &self,
+        id: i32,
+        user_id: i32,
+        status: &str,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"UPDATE oauth2_device_codes SET status = $3, user_id = $2
+            WHERE id = $1 AND status = $4 AND expires_at > CURRENT_TIMESTAMP"#,
+            id,
+            user_id,
+            status,
+            DEVICE_CODE_PENDING
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn record_device_poll(&self, id: i32, interval_seconds: i32) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"UPDATE oauth2_device_codes
+            SET last_polled_at = CURRENT_TIMESTAMP, interval_seconds = $2
+            WHERE id = $1"#,
+            id,
+            interval_seconds
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn mark_device_code_used(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"UPDATE oauth2_device_codes SET used_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND used_at IS NULL"#,
+            id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
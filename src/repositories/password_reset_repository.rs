@@ -0,0 +1,93 @@
+use crate::error::AppError;
+use crate::models::PasswordResetToken;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+#[async_trait]
+pub trait PasswordResetRepository: Send + Sync {
+    /// Stores a new token for the user, discarding any earlier ones still outstanding.
+    async fn create_token(
+        &self,
+        user_id: i32,
+        token_hash: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError>;
+    async fn get_token_by_hash(&self, token_hash: &str) -> Result<PasswordResetToken, AppError>;
+    /// Marks the token as used, returning `false` if it was already used or has expired.
+    async fn mark_used(&self, id: i32) -> Result<bool, AppError>;
+}
+
+pub struct PasswordResetRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl PasswordResetRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PasswordResetRepository for PasswordResetRepositoryImpl {
+    async fn create_token(
+        &self,
+        user_id: i32,
+        token_hash: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            "DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            r#"INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+            VALUES ($1, $2, $3)"#,
+            user_id,
+            token_hash,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn get_token_by_hash(&self, token_hash: &str) -> Result<PasswordResetToken, AppError> {
+        let token = sqlx::query_as!(
+            PasswordResetToken,
+            r#"SELECT id, user_id, token_hash, expires_at, used_at
+            FROM password_reset_tokens WHERE token_hash = $1"#,
+            token_hash
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(token)
+    }
+
+    async fn mark_used(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"UPDATE password_reset_tokens SET used_at = NOW()
+            WHERE id = $1 AND used_at IS NULL AND expires_at > NOW()"#,
+            id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,100 @@
+use crate::error::AppError;
+use crate::models::role::Role;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait PermissionRepository: Send + Sync {
+    async fn get_user_roles(&self, user_id: i32) -> Result<Vec<Role>, AppError>;
+    async fn user_has_permission(&self, user_id: i32, permission: &str) -> Result<bool, AppError>;
+    async fn assign_role(&self, user_id: i32, role: &str) -> Result<(), AppError>;
+    async fn remove_role(&self, user_id: i32, role: &str) -> Result<(), AppError>;
+}
+
+pub struct PermissionRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl PermissionRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PermissionRepository for PermissionRepositoryImpl {
+    async fn get_user_roles(&self, user_id: i32) -> Result<Vec<Role>, AppError> {
+        let roles = sqlx::query_as!(
+            Role,
+            r#"SELECT r.id, r.name
+            FROM roles r
+            JOIN user_roles ur ON r.id = ur.role_id
+            WHERE ur.user_id = $1
+            ORDER BY r.name"#,
+            user_id
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(roles)
+    }
+
+    async fn user_has_permission(&self, user_id: i32, permission: &str) -> Result<bool, AppError> {
+        let row = sqlx::query!(
+            r#"SELECT EXISTS (
+                SELECT 1
+                FROM user_roles ur
+                JOIN role_permissions rp ON ur.role_id = rp.role_id
+                JOIN permissions p ON rp.permission_id = p.id
+                WHERE ur.user_id = $1 AND p.name = $2
+            ) as "allowed!""#,
+            user_id,
+            permission
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(row.allowed)
+    }
+
+    async fn assign_role(&self, user_id: i32, role: &str) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"INSERT INTO user_roles (user_id, role_id)
+            SELECT $1, id FROM roles WHERE name = $2
+            ON CONFLICT DO NOTHING"#,
+            user_id,
+            role
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        if result.rows_affected() == 0 {
+            // Either the role does not exist or the user already has it.
+            sqlx::query!("SELECT id FROM roles WHERE name = $1", role)
+                .fetch_optional(&*self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?
+                .ok_or(AppError::NotFound)?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_role(&self, user_id: i32, role: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"DELETE FROM user_roles
+            WHERE user_id = $1 AND role_id = (SELECT id FROM roles WHERE name = $2)"#,
+            user_id,
+            role
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::error::AppError;
+use crate::models::RateLimit;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait RateLimitRepository: Send + Sync {
+    /// Counts a request against `key`, starting a new window if the current one is
+    /// older than `window_seconds`, and returns the updated counter.
+    async fn hit(&self, key: &str, window_seconds: i64) -> Result<RateLimit, AppError>;
+}
+
+pub struct RateLimitRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl RateLimitRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RateLimitRepository for RateLimitRepositoryImpl {
+    async fn hit(&self, key: &str, window_seconds: i64) -> Result<RateLimit, AppError> {
+        let rate_limit = sqlx::query_as!(
+            RateLimit,
+            r#"INSERT INTO rate_limits (key, hits, window_started_at)
+            VALUES ($1, 1, NOW())
+            ON CONFLICT (key) DO UPDATE SET
+                hits = CASE
+                    WHEN rate_limits.window_started_at <= NOW() - make_interval(secs => $2)
+                    THEN 1
+                    ELSE rate_limits.hits + 1
+                END,
+                window_started_at = CASE
+                    WHEN rate_limits.window_started_at <= NOW() - make_interval(secs => $2)
+                    THEN NOW()
+                    ELSE rate_limits.window_started_at
+                END
+            RETURNING key, hits, window_started_at"#,
+            key,
+            window_seconds as f64
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rate_limit)
+    }
+}
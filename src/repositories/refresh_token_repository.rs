@@ -0,0 +1,110 @@
+use crate::error::AppError;
+use crate::models::RefreshToken;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+#[async_trait]
+pub trait RefreshTokenRepository: Send + Sync {
+    async fn create_token(
+        &self,
+        user_id: i32,
+        token_hash: &str,
+        family_id: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<RefreshToken, AppError>;
+    async fn get_token_by_hash(&self, token_hash: &str) -> Result<RefreshToken, AppError>;
+    /// Marks the token as used, returning `false` if it had already been used.
+    async fn mark_used(&self, id: i32) -> Result<bool, AppError>;
+    async fn revoke_family(&self, family_id: &str) -> Result<(), AppError>;
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError>;
+}
+
+pub struct RefreshTokenRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl RefreshTokenRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for RefreshTokenRepositoryImpl {
+    async fn create_token(
+        &self,
+        user_id: i32,
+        token_hash: &str,
+        family_id: &str,
+        expires_at: OffsetDateTime,
+    ) -> Result<RefreshToken, AppError> {
+        let token = sqlx::query_as!(
+            RefreshToken,
+            r#"INSERT INTO refresh_tokens (user_id, token_hash, family_id, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, token_hash, family_id, expires_at, used_at, revoked_at"#,
+            user_id,
+            token_hash,
+            family_id,
+            expires_at
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(token)
+    }
+
+    async fn get_token_by_hash(&self, token_hash: &str) -> Result<RefreshToken, AppError> {
+        let token = sqlx::query_as!(
+            RefreshToken,
+            r#"SELECT id, user_id, token_hash, family_id, expires_at, used_at, revoked_at
+            FROM refresh_tokens WHERE token_hash = $1"#,
+            token_hash
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(token)
+    }
+
+    async fn mark_used(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1 AND used_at IS NULL",
+            id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn revoke_family(&self, family_id: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+            family_id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+            user_id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
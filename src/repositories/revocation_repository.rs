@@ -0,0 +1,107 @@
+use crate::error::AppError;
+use crate::models::{RevokedToken, UserTokenRevocation};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+#[async_trait]
+pub trait RevocationRepository: Send + Sync {
+    async fn revoke_token(
+        &self,
+        jti: &str,
+        user_id: i32,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError>;
+    async fn revoke_user_tokens(
+        &self,
+        user_id: i32,
+        revoked_before: OffsetDateTime,
+    ) -> Result<(), AppError>;
+    async fn get_active_revoked_tokens(&self) -> Result<Vec<RevokedToken>, AppError>;
+    async fn get_user_revocations(&self) -> Result<Vec<UserTokenRevocation>, AppError>;
+}
+
+pub struct RevocationRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl RevocationRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RevocationRepository for RevocationRepositoryImpl {
+    async fn revoke_token(
+        &self,
+        jti: &str,
+        user_id: i32,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            "INSERT INTO revoked_tokens (jti, user_id, expires_at) VALUES ($1, $2, $3) ON CONFLICT (jti) DO NOTHING",
+            jti,
+            user_id,
+            expires_at
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn revoke_user_tokens(
+        &self,
+        user_id: i32,
+        revoked_before: OffsetDateTime,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"INSERT INTO user_token_revocations (user_id, revoked_before) VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET revoked_before = EXCLUDED.revoked_before"#,
+            user_id,
+            revoked_before
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn get_active_revoked_tokens(&self) -> Result<Vec<RevokedToken>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        // Expired tokens are rejected on their own, so there is no need to keep them.
+        sqlx::query!("DELETE FROM revoked_tokens WHERE expires_at < NOW()")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        let tokens = sqlx::query_as!(
+            RevokedToken,
+            "SELECT jti, user_id, expires_at FROM revoked_tokens"
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(tokens)
+    }
+
+    async fn get_user_revocations(&self) -> Result<Vec<UserTokenRevocation>, AppError> {
+        let revocations = sqlx::query_as!(
+            UserTokenRevocation,
+            "SELECT user_id, revoked_before FROM user_token_revocations"
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(revocations)
+    }
+}
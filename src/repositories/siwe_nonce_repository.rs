@@ -0,0 +1,59 @@
+use crate::error::AppError;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+#[async_trait]
+pub trait SiweNonceRepository: Send + Sync {
+    async fn create_nonce(&self, nonce: &str, expires_at: OffsetDateTime) -> Result<(), AppError>;
+    /// Deletes an unexpired nonce, returning `false` if there was none to consume.
+    async fn consume_nonce(&self, nonce: &str) -> Result<bool, AppError>;
+}
+
+pub struct SiweNonceRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl SiweNonceRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SiweNonceRepository for SiweNonceRepositoryImpl {
+    async fn create_nonce(&self, nonce: &str, expires_at: OffsetDateTime) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query!("DELETE FROM siwe_nonces WHERE expires_at < NOW()")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            "INSERT INTO siwe_nonces (nonce, expires_at) VALUES ($1, $2)",
+            nonce,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn consume_nonce(&self, nonce: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM siwe_nonces WHERE nonce = $1 AND expires_at > NOW()",
+            nonce
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
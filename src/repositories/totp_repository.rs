@@ -0,0 +1,162 @@
+use crate::error::AppError;
+use crate::models::UserTotp;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait TotpRepository: Send + Sync {
+    async fn get_totp(&self, user_id: i32) -> Result<UserTotp, AppError>;
+    /// Stores a new unconfirmed secret, replacing any earlier unconfirmed one.
+    /// Returns `false` if the user already has a confirmed secret.
+    async fn save_pending_secret(&self, user_id: i32, secret: &str) -> Result<bool, AppError>;
+    /// Confirms the secret and replaces the user's recovery codes in one transaction.
+    async fn confirm(
+        &self,
+        user_id: i32,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), AppError>;
+    /// Records `step` as used, returning `false` if it or a later step was already used.
+    async fn record_step(&self, user_id: i32, step: i64) -> Result<bool, AppError>;
+    /// Marks a recovery code as used, returning `false` if it does not exist or was already used.
+    async fn use_recovery_code(&self, user_id: i32, code_hash: &str) -> Result<bool, AppError>;
+    async fn delete_totp(&self, user_id: i32) -> Result<(), AppError>;
+}
+
+pub struct TotpRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl TotpRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TotpRepository for TotpRepositoryImpl {
+    async fn get_totp(&self, user_id: i32) -> Result<UserTotp, AppError> {
+        let totp = sqlx::query_as!(
+            UserTotp,
+            "SELECT user_id, secret, confirmed_at, last_used_step FROM user_totp WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(totp)
+    }
+
+    async fn save_pending_secret(&self, user_id: i32, secret: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"INSERT INTO user_totp (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = EXCLUDED.secret, last_used_step = 0, created_at = NOW()
+            WHERE user_totp.confirmed_at IS NULL"#,
+            user_id,
+            secret
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn confirm(
+        &self,
+        user_id: i32,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        let result = sqlx::query!(
+            r#"UPDATE user_totp SET confirmed_at = NOW(), last_used_step = $2
+            WHERE user_id = $1 AND confirmed_at IS NULL"#,
+            user_id,
+            step
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        if result.rows_affected() == 0 {
+            return Err(AppError::BadRequest(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        sqlx::query!(
+            "DELETE FROM totp_recovery_codes WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            r#"INSERT INTO totp_recovery_codes (user_id, code_hash)
+            SELECT $1, UNNEST($2::VARCHAR[])"#,
+            user_id,
+            recovery_code_hashes
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn record_step(&self, user_id: i32, step: i64) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1 AND last_used_step < $2",
+            user_id,
+            step
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn use_recovery_code(&self, user_id: i32, code_hash: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"UPDATE totp_recovery_codes SET used_at = NOW()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"#,
+            user_id,
+            code_hash
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn delete_totp(&self, user_id: i32) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query!(
+            "DELETE FROM totp_recovery_codes WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
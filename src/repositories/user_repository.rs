@@ -1,4 +1,5 @@
 use crate::error::AppError;
+use crate::models::role::{ADMIN_ROLE, DEFAULT_ROLE};
 use crate::models::user::User;
 use async_trait::async_trait;
 use sqlx::PgPool;
@@ -6,14 +7,22 @@ use std::sync::Arc;
 
 #[async_trait]
 pub trait UserRepository: Send + Sync {
+    /// Creates the user along with their initial role: admin if they are the only user,
+    /// the default role otherwise.
     async fn create_user(
         &self,
         username: &str,
-        email: &str,
-        password_hash: &str,
+        email: Option<&str>,
+        password_hash: Option<&str>,
     ) -> Result<User, AppError>;
     async fn get_user_by_username(&self, username: &str) -> Result<User, AppError>;
+    async fn get_user_by_email(&self, email: &str) -> Result<User, AppError>;
+    async fn get_user_by_id(&self, id: i32) -> Result<User, AppError>;
     async fn get_all_users(&self) -> Result<Vec<User>, AppError>;
+    async fn update_password_hash(&self, id: i32, password_hash: &str) -> Result<(), AppError>;
+    /// Marks `email` as verified, unless the user has changed it in the meantime.
+    async fn mark_email_verified(&self, id: i32, email: &str) -> Result<bool, AppError>;
+    async fn delete_user(&self, id: i32) -> Result<(), AppError>;
 }
 
 pub struct UserRepositoryImpl {
@@ -31,27 +40,52 @@ impl UserRepository for UserRepositoryImpl {
     async fn create_user(
         &self,
         username: &str,
-        email: &str,
-        password_hash: &str,
+        email: Option<&str>,
+        password_hash: Option<&str>,
     ) -> Result<User, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        // Serialises concurrent sign-ups so only the very first user can become admin.
+        sqlx::query!("LOCK TABLE user_roles IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
         let user = sqlx::query_as!(
             User,
-            "INSERT INTO users (username, email, password_hash) VALUES ($1, $2, $3) RETURNING id, username, email, password_hash",
+            "INSERT INTO users (username, email, password_hash) VALUES ($1, $2, $3) RETURNING id, username, email, password_hash, email_verified_at",
             username,
             email,
             password_hash
         )
-        .fetch_one(&*self.pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(AppError::DatabaseError)?;
 
+        sqlx::query!(
+            r#"INSERT INTO user_roles (user_id, role_id)
+            SELECT $1, id FROM roles
+            WHERE name = CASE
+                WHEN EXISTS (SELECT 1 FROM users WHERE id <> $1) THEN $3
+                ELSE $2
+            END"#,
+            user.id,
+            ADMIN_ROLE,
+            DEFAULT_ROLE
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
         Ok(user)
     }
 
     async fn get_user_by_username(&self, username: &str) -> Result<User, AppError> {
         let user = sqlx::query_as!(
             User,
-            "SELECT id, username, email, password_hash FROM users WHERE username = $1",
+            "SELECT id, username, email, password_hash, email_verified_at FROM users WHERE username = $1",
             username
         )
         .fetch_optional(&*self.pool)
@@ -62,12 +96,78 @@ impl UserRepository for UserRepositoryImpl {
         Ok(user)
     }
 
+    async fn get_user_by_email(&self, email: &str) -> Result<User, AppError> {
+        let user = sqlx::query_as!(
+            User,
+            "SELECT id, username, email, password_hash, email_verified_at FROM users WHERE email = $1",
+            email
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(user)
+    }
+
+    async fn get_user_by_id(&self, id: i32) -> Result<User, AppError> {
+        let user = sqlx::query_as!(
+            User,
+            "SELECT id, username, email, password_hash, email_verified_at FROM users WHERE id = $1",
+            id
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(user)
+    }
+
     async fn get_all_users(&self) -> Result<Vec<User>, AppError> {
-        let users = sqlx::query_as!(User, "SELECT id, username, email, password_hash FROM users")
-            .fetch_all(&*self.pool)
+        let users = sqlx::query_as!(
+            User,
+            "SELECT id, username, email, password_hash, email_verified_at FROM users"
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(users)
+    }
+
+    async fn update_password_hash(&self, id: i32, password_hash: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE users SET password_hash = $1 WHERE id = $2",
+            password_hash,
+            id
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, id: i32, email: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE id = $1 AND email = $2",
+            id,
+            email
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_user(&self, id: i32) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM users WHERE id = $1", id)
+            .execute(&*self.pool)
             .await
             .map_err(AppError::DatabaseError)?;
 
-        Ok(users)
+        Ok(())
     }
 }
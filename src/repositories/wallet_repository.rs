@@ -0,0 +1,61 @@
+use crate::error::AppError;
+use crate::models::WalletAddress;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait WalletRepository: Send + Sync {
+    async fn get_wallet_by_address(&self, address: &str) -> Result<WalletAddress, AppError>;
+    /// Returns `None` when the address is already linked, which may be to another user.
+    async fn create_wallet(
+        &self,
+        user_id: i32,
+        address: &str,
+    ) -> Result<Option<WalletAddress>, AppError>;
+}
+
+pub struct WalletRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl WalletRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WalletRepository for WalletRepositoryImpl {
+    async fn get_wallet_by_address(&self, address: &str) -> Result<WalletAddress, AppError> {
+        let wallet = sqlx::query_as!(
+            WalletAddress,
+            "SELECT id, user_id, address FROM wallet_addresses WHERE address = $1",
+            address
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(wallet)
+    }
+
+    async fn create_wallet(
+        &self,
+        user_id: i32,
+        address: &str,
+    ) -> Result<Option<WalletAddress>, AppError> {
+        let wallet = sqlx::query_as!(
+            WalletAddress,
+            "INSERT INTO wallet_addresses (user_id, address) VALUES ($1, $2) ON CONFLICT (address) DO NOTHING RETURNING id, user_id, address",
+            user_id,
+            address
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(wallet)
+    }
+}
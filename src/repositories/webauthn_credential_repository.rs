@@ -0,0 +1,139 @@
+use crate::error::AppError;
+use crate::models::WebauthnCredential;
+use async_trait::async_trait;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use webauthn_rs::prelude::Passkey;
+
+#[async_trait]
+pub trait WebauthnCredentialRepository: Send + Sync {
+    async fn get_user_handle(&self, user_id: i32) -> Result<Option<Uuid>, AppError>;
+    async fn get_credentials_for_user(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<WebauthnCredential>, AppError>;
+    async fn get_credential_by_id(
+        &self,
+        credential_id: &str,
+    ) -> Result<WebauthnCredential, AppError>;
+    async fn create_credential(
+        &self,
+        user_id: i32,
+        user_handle: Uuid,
+        credential_id: &str,
+        passkey: &Passkey,
+        name: &str,
+    ) -> Result<(), AppError>;
+    /// Stores the passkey after a successful assertion, along with its new sign counter.
+    async fn record_use(&self, id: i32, passkey: &Passkey, sign_count: i64)
+        -> Result<(), AppError>;
+}
+
+pub struct WebauthnCredentialRepositoryImpl {
+    pool: Arc<PgPool>,
+}
+
+impl WebauthnCredentialRepositoryImpl {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WebauthnCredentialRepository for WebauthnCredentialRepositoryImpl {
+    async fn get_user_handle(&self, user_id: i32) -> Result<Option<Uuid>, AppError> {
+        let row = sqlx::query!(
+            "SELECT user_handle FROM webauthn_credentials WHERE user_id = $1 LIMIT 1",
+            user_id
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(row.map(|row| row.user_handle))
+    }
+
+    async fn get_credentials_for_user(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<WebauthnCredential>, AppError> {
+        let credentials = sqlx::query_as!(
+            WebauthnCredential,
+            r#"SELECT id, user_id, user_handle, credential_id, passkey as "passkey: Json<Passkey>",
+                sign_count, name, created_at, last_used_at
+            FROM webauthn_credentials WHERE user_id = $1 ORDER BY created_at"#,
+            user_id
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(credentials)
+    }
+
+    async fn get_credential_by_id(
+        &self,
+        credential_id: &str,
+    ) -> Result<WebauthnCredential, AppError> {
+        let credential = sqlx::query_as!(
+            WebauthnCredential,
+            r#"SELECT id, user_id, user_handle, credential_id, passkey as "passkey: Json<Passkey>",
+                sign_count, name, created_at, last_used_at
+            FROM webauthn_credentials WHERE credential_id = $1"#,
+            credential_id
+        )
+        .fetch_optional(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .ok_or(AppError::NotFound)?;
+
+        Ok(credential)
+    }
+
+    async fn create_credential(
+        &self,
+        user_id: i32,
+        user_handle: Uuid,
+        credential_id: &str,
+        passkey: &Passkey,
+        name: &str,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"INSERT INTO webauthn_credentials (user_id, user_handle, credential_id, passkey, name)
+            VALUES ($1, $2, $3, $4, $5)"#,
+            user_id,
+            user_handle,
+            credential_id,
+            Json(passkey) as _,
+            name
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn record_use(
+        &self,
+        id: i32,
+        passkey: &Passkey,
+        sign_count: i64,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"UPDATE webauthn_credentials
+            SET passkey = $2, sign_count = $3, last_used_at = NOW()
+            WHERE id = $1"#,
+            id,
+            Json(passkey) as _,
+            sign_count
+        )
+        .execute(&*self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
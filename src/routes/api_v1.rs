@@ -1,12 +1,20 @@
 use axum::{
-    routing::{get, post},
+    middleware,
+    response::Redirect,
+    routing::{delete, get, post, put},
     Router,
 };
 use std::sync::Arc;
 
 use crate::{
-    handlers::product,
-    services::{AuthService, OAuthService, SiweService, UserService},
+    config::TrustedProxyConfig,
+    handlers::{api_key, oauth2, password, product, two_factor, webauthn, well_known},
+    middleware::{csrf_protect, require_auth, require_permission, require_session},
+    services::{
+        ApiKeyService, AuthService, EmailVerificationService, JwtKeys, LoginThrottleService,
+        OAuth2ServerService, OAuthService, PasswordResetService, PermissionService, SiweService,
+        TotpService, UserService, WebauthnService,
+    },
 };
 use crate::{
     handlers::{self, auth},
@@ -21,40 +29,160 @@ pub struct AppState {
     pub oauth_service: Arc<dyn OAuthService>,
     pub siwe_service: Arc<dyn SiweService>,
     pub product_service: Arc<dyn ProductService>,
+    pub permission_service: Arc<dyn PermissionService>,
+    pub email_verification_service: Arc<dyn EmailVerificationService>,
+    pub password_reset_service: Arc<dyn PasswordResetService>,
+    pub totp_service: Arc<dyn TotpService>,
+    pub webauthn_service: Arc<dyn WebauthnService>,
+    pub login_throttle_service: Arc<dyn LoginThrottleService>,
+    pub jwt_keys: Arc<JwtKeys>,
+    pub api_key_service: Arc<dyn ApiKeyService>,
+    pub oauth2_server_service: Arc<dyn OAuth2ServerService>,
+    pub trusted_proxies: Arc<TrustedProxyConfig>,
 }
 
-pub fn create_router(
-    user_service: Arc<dyn UserService>,
-    auth_service: Arc<dyn AuthService>,
-    oauth_service: Arc<dyn OAuthService>,
-    siwe_service: Arc<dyn SiweService>,
-    product_service: Arc<dyn ProductService>,
-) -> Router {
-    let state = AppState {
-        user_service,
-        auth_service,
-        oauth_service,
-        siwe_service,
-        product_service,
-    };
+pub fn create_router(state: AppState) -> Router {
+    let users = Router::new()
+        .route("/users", get(handlers::user::get_users))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_permission("users:read"),
+        ));
+
+    let roles = Router::new()
+        .route("/users/:id/roles", post(handlers::user::assign_role))
+        .route(
+            "/users/:id/roles/:role",
+            delete(handlers::user::remove_role),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_permission("roles:manage"),
+        ));
+
+    let lockouts = Router::new()
+        .route("/users/:id/unlock", post(handlers::user::unlock_user))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_permission("users:unlock"),
+        ));
+
+    let oauth2_clients = Router::new()
+        .route(
+            "/oauth2/clients",
+            get(oauth2::list_clients).post(oauth2::create_client),
+        )
+        .route("/oauth2/clients/:client_id", delete(oauth2::delete_client))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_permission("oauth2_clients:manage"),
+        ));
+
+    let catalog = Router::new()
+        .route("/products", post(product::create_product))
+        .route("/products/new", get(product::new_product))
+        .route(
+            "/products/:id",
+            put(product::update_product).delete(product::delete_product),
+        )
+        .route("/products/:id/edit", get(product::edit_product))
+        .route("/bundles", post(product::create_bundle))
+        .route("/bundles/new", get(product::new_bundle))
+        .route(
+            "/bundles/:id",
+            put(product::update_bundle).delete(product::delete_bundle),
+        )
+        .route("/bundles/:id/edit", get(product::edit_bundle))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_permission("products:write"),
+        ));
+
+    // Managing the account itself needs a real sign-in, not an API key.
+    let account = Router::new()
+        .route("/logout/all", post(auth::logout_all))
+        .route("/siwe/link", post(auth::siwe_link))
+        .route("/account/2fa", get(two_factor::show_two_factor))
+        .route("/account/2fa/enroll", post(two_factor::enroll))
+        .route("/account/2fa/confirm", post(two_factor::confirm))
+        .route("/account/2fa/disable", post(two_factor::disable))
+        .route(
+            "/account/api-keys",
+            get(api_key::list_api_keys).post(api_key::create_api_key),
+        )
+        .route("/account/api-keys/:id", delete(api_key::revoke_api_key))
+        .route(
+            "/webauthn/register/start",
+            post(webauthn::start_registration),
+        )
+        .route(
+            "/webauthn/register/finish",
+            post(webauthn::finish_registration),
+        )
+        .route_layer(middleware::from_fn(require_session));
+
+    // Routes that change data or expose user details require a valid token,
+    // and most of them a specific permission on top.
+    let protected = Router::new()
+        .merge(account)
+        .merge(users)
+        .merge(roles)
+        .merge(lockouts)
+        .merge(oauth2_clients)
+        .merge(catalog)
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_auth));
+
+    // Called by API and OAuth2 clients that send their credentials in the request body,
+    // so there is no ambient cookie to protect and no CSRF token to check.
+    let client_endpoints = Router::new()
+        .route("/token/refresh", post(auth::refresh_token))
+        .route(
+            "/oauth2/device_authorization",
+            post(oauth2::device_authorization),
+        )
+        .route("/oauth2/token", post(oauth2::token))
+        .route("/oauth2/introspect", post(oauth2::introspect))
+        .route("/oauth2/revoke", post(oauth2::revoke));
 
     Router::new()
-        .route("/", get(handlers::user::get_users))
-        .route("/users", get(handlers::user::get_users))
+        .route("/", get(|| async { Redirect::to("/products") }))
         .route("/register", get(auth::show_register).post(auth::register))
         .route("/login", get(auth::show_login).post(auth::login))
+        .route("/login/mfa", post(auth::login_mfa))
+        .route("/verify-email", get(auth::verify_email))
+        .route("/verify-email/resend", post(auth::resend_verification))
+        .route(
+            "/password/forgot",
+            get(password::show_forgot_password).post(password::forgot_password),
+        )
+        .route(
+            "/password/reset",
+            get(password::show_reset_password).post(password::reset_password),
+        )
         .route("/logout", post(auth::logout))
-        .route("/oauth/login", get(auth::oauth_login))
-        .route("/oauth/callback", get(auth::oauth_callback))
+        .route("/oauth/:provider/login", get(auth::oauth_login))
+        .route("/oauth/:provider/callback", get(auth::oauth_callback))
+        .route("/siwe/nonce", get(auth::siwe_nonce))
         .route("/siwe/login", post(auth::siwe_login))
-        .route("/products", get(product::get_products).post(product::create_product))
-        .route("/products/new", get(product::new_product))
-        .route("/products/:id", get(product::get_product).put(product::update_product).delete(product::delete_product))
-        .route("/products/:id/edit", get(product::edit_product))
-        .route("/bundles", get(product::get_bundles).post(product::create_bundle))
-        .route("/bundles/new", get(product::new_bundle))
-        .route("/bundles/:id", get(product::get_bundle).put(product::update_bundle).delete(product::delete_bundle))
-        .route("/bundles/:id/edit", get(product::edit_bundle))
+        .route("/webauthn/login/start", post(webauthn::start_login))
+        .route("/webauthn/login/finish", post(webauthn::finish_login))
+        .route("/.well-known/jwks.json", get(well_known::jwks))
+        .route(
+            "/oauth2/authorize",
+            get(oauth2::authorize).post(oauth2::consent),
+        )
+        .route(
+            "/device",
+            get(oauth2::show_device).post(oauth2::verify_device),
+        )
+        .route("/oauth2/userinfo", get(oauth2::userinfo))
+        .route("/products", get(product::get_products))
+        .route("/products/:id", get(product::get_product))
+        .route("/bundles", get(product::get_bundles))
+        .route("/bundles/:id", get(product::get_bundle))
+        .merge(protected)
         .nest_service("/static", ServeDir::new("static"))
+        .layer(middleware::from_fn(csrf_protect))
+        .merge(client_endpoints)
         .with_state(state)
 }
@@ -0,0 +1,142 @@
+use crate::error::AppError;
+use crate::models::{ApiKey, FieldError, NewApiKey, User};
+use crate::repositories::{ApiKeyRepository, UserRepository};
+use crate::services::PermissionService;
+use crate::utils::crypto::{random_token, sha256_hex};
+use async_trait::async_trait;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// Marks a bearer credential as an API key rather than a JWT.
+pub const API_KEY_PREFIX: &str = "ak_";
+
+#[async_trait]
+pub trait ApiKeyService: Send + Sync {
+    /// Creates a key limited to `scopes`, each of which must be a permission the user holds.
+    async fn create_key(
+        &self,
+        user_id: i32,
+        name: &str,
+        scopes: &[String],
+        expires_in_days: u32,
+    ) -> Result<NewApiKey, AppError>;
+    async fn list_keys(&self, user_id: i32) -> Result<Vec<ApiKey>, AppError>;
+    async fn revoke_key(&self, user_id: i32, id: i32) -> Result<(), AppError>;
+    /// Resolves a presented key to its owner, rejecting revoked and expired keys.
+    async fn authenticate(&self, key: &str) -> Result<(User, ApiKey), AppError>;
+}
+
+pub struct ApiKeyServiceImpl {
+    api_key_repository: Arc<dyn ApiKeyRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    permission_service: Arc<dyn PermissionService>,
+}
+
+impl ApiKeyServiceImpl {
+    pub fn new(
+        api_key_repository: Arc<dyn ApiKeyRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        permission_service: Arc<dyn PermissionService>,
+    ) -> Self {
+        Self {
+            api_key_repository,
+            user_repository,
+            permission_service,
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyService for ApiKeyServiceImpl {
+    async fn create_key(
+        &self,
+        user_id: i32,
+        name: &str,
+        scopes: &[String],
+        expires_in_days: u32,
+    ) -> Result<NewApiKey, AppError> {
+        let name = name.trim();
+        let mut errors = Vec::new();
+        if name.is_empty() || name.chars().count() > 100 {
+            errors.push(FieldError::new(
+                "name",
+                "Name must be between 1 and 100 characters",
+            ));
+        }
+        for scope in scopes {
+            if !self
+                .permission_service
+                .has_permission(user_id, scope)
+                .await?
+            {
+                errors.push(FieldError::new(
+                    "scopes",
+                    format!("You don't have the {} permission", scope),
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::Validation(errors));
+        }
+
+        // e.g. ak_Xy12Ab34_<40 random characters>
+        let prefix = format!("{}{}", API_KEY_PREFIX, random_token(8));
+        let key = format!("{}_{}", prefix, random_token(40));
+        let expires_at = (expires_in_days > 0)
+            .then(|| OffsetDateTime::now_utc() + Duration::days(expires_in_days.into()));
+
+        let api_key = self
+            .api_key_repository
+            .create_key(
+                user_id,
+                name,
+                &prefix,
+                &sha256_hex(&key),
+                scopes,
+                expires_at,
+            )
+            .await?;
+
+        Ok(NewApiKey { key, api_key })
+    }
+
+    async fn list_keys(&self, user_id: i32) -> Result<Vec<ApiKey>, AppError> {
+        self.api_key_repository.get_keys_for_user(user_id).await
+    }
+
+    async fn revoke_key(&self, user_id: i32, id: i32) -> Result<(), AppError> {
+        if !self.api_key_repository.revoke_key(id, user_id).await? {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn authenticate(&self, key: &str) -> Result<(User, ApiKey), AppError> {
+        let api_key = match self
+            .api_key_repository
+            .get_key_by_hash(&sha256_hex(key))
+            .await
+        {
+            Ok(api_key) => api_key,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+
+        if api_key.revoked_at.is_some()
+            || api_key
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+        {
+            return Err(AppError::Unauthorized);
+        }
+
+        let user = match self.user_repository.get_user_by_id(api_key.user_id).await {
+            Ok(user) => user,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+        self.api_key_repository.record_use(api_key.id).await?;
+
+        Ok((user, api_key))
+    }
+}
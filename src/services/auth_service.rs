@@ -1,50 +1,279 @@
+use crate::config::AccessTokenConfig;
 use crate::error::AppError;
-use crate::models::auth::{AuthResponse, LoginRequest, RegisterRequest};
-use crate::models::User;
-use crate::repositories::UserRepository;
+use crate::models::{
+    AuthResponse, Claims, ExternalIdentity, LoginRequest, LoginResult, MfaChallenge,
+    RefreshRequest, RegisterRequest, User,
+};
+use crate::repositories::{
+    IdentityRepository, OAuth2Repository, RefreshTokenRepository, UserRepository, WalletRepository,
+};
+use crate::services::{
+    EmailVerificationService, JwtKeys, LoginThrottleService, PasswordHasher, PasswordPolicy,
+    PermissionService, RevocationService, TotpService,
+};
+use crate::utils::crypto::{random_token, sha256_hex};
 use async_trait::async_trait;
-use bcrypt::{hash, verify};
-use jsonwebtoken::{encode, EncodingKey, Header};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use time::OffsetDateTime;
+use tokio::sync::OnceCell;
+
+const REFRESH_TOKEN_TTL: time::Duration = time::Duration::days(30);
+const MFA_TOKEN_TTL: Duration = Duration::from_secs(5 * 60);
+/// Keeps an MFA pending token from being accepted as an access token and vice versa.
+const MFA_AUDIENCE: &str = "mfa-pending";
+
+/// Proves the password step of a login succeeded, and nothing more.
+#[derive(Serialize, Deserialize)]
+struct MfaClaims {
+    sub: i32,
+    aud: String,
+    exp: u64,
+    iat: u64,
+    jti: String,
+}
 
 #[async_trait]
 pub trait AuthService: Send + Sync {
     async fn register(&self, req: RegisterRequest) -> Result<AuthResponse, AppError>;
-    async fn login(&self, req: LoginRequest) -> Result<AuthResponse, AppError>;
+    async fn login(&self, req: LoginRequest, client_ip: IpAddr) -> Result<LoginResult, AppError>;
+    /// Completes a login that returned `LoginResult::MfaRequired`.
+    async fn verify_mfa(
+        &self,
+        mfa_token: &str,
+        code: &str,
+        client_ip: IpAddr,
+    ) -> Result<AuthResponse, AppError>;
+    async fn refresh(&self, req: RefreshRequest) -> Result<AuthResponse, AppError>;
+    async fn login_with_identity(
+        &self,
+        identity: ExternalIdentity,
+    ) -> Result<AuthResponse, AppError>;
+    async fn login_with_wallet(&self, address: &str) -> Result<AuthResponse, AppError>;
+    async fn link_wallet(&self, user_id: i32, address: &str) -> Result<(), AppError>;
+    /// Issues tokens for a user whose passkey assertion has already been verified.
+    async fn login_with_passkey(&self, user_id: i32) -> Result<AuthResponse, AppError>;
+    async fn authenticate(&self, token: &str) -> Result<User, AppError>;
+    async fn logout(&self, token: &str) -> Result<(), AppError>;
+    async fn logout_all(&self, user_id: i32) -> Result<(), AppError>;
 }
 
 pub struct AuthServiceImpl {
     user_repository: Arc<dyn UserRepository>,
+    refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+    oauth2_repository: Arc<dyn OAuth2Repository>,
+    identity_repository: Arc<dyn IdentityRepository>,
+    wallet_repository: Arc<dyn WalletRepository>,
+    revocation_service: Arc<dyn RevocationService>,
+    permission_service: Arc<dyn PermissionService>,
+    password_hasher: Arc<dyn PasswordHasher>,
+    password_policy: Arc<dyn PasswordPolicy>,
+    email_verification_service: Arc<dyn EmailVerificationService>,
+    totp_service: Arc<dyn TotpService>,
+    login_throttle_service: Arc<dyn LoginThrottleService>,
+    jwt_keys: Arc<JwtKeys>,
+    access_token_config: AccessTokenConfig,
+    /// Signs MFA pending tokens, which never leave this service.
     jwt_secret: String,
+    require_email_verification: bool,
+    /// Hash of a random password, verified against when the account has none.
+    dummy_hash: OnceCell<String>,
 }
 
 impl AuthServiceImpl {
-    pub fn new(user_repository: Arc<dyn UserRepository>, jwt_secret: String) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+        oauth2_repository: Arc<dyn OAuth2Repository>,
+        identity_repository: Arc<dyn IdentityRepository>,
+        wallet_repository: Arc<dyn WalletRepository>,
+        revocation_service: Arc<dyn RevocationService>,
+        permission_service: Arc<dyn PermissionService>,
+        password_hasher: Arc<dyn PasswordHasher>,
+        password_policy: Arc<dyn PasswordPolicy>,
+        email_verification_service: Arc<dyn EmailVerificationService>,
+        totp_service: Arc<dyn TotpService>,
+        login_throttle_service: Arc<dyn LoginThrottleService>,
+        jwt_keys: Arc<JwtKeys>,
+        access_token_config: AccessTokenConfig,
+        jwt_secret: String,
+        require_email_verification: bool,
+    ) -> Self {
         Self {
             user_repository,
+            refresh_token_repository,
+            oauth2_repository,
+            identity_repository,
+            wallet_repository,
+            revocation_service,
+            permission_service,
+            password_hasher,
+            password_policy,
+            email_verification_service,
+            totp_service,
+            login_throttle_service,
+            jwt_keys,
+            access_token_config,
             jwt_secret,
+            require_email_verification,
+            dummy_hash: OnceCell::new(),
         }
     }
 
-    fn generate_token(&self, user_id: i32) -> Result<String, AppError> {
-        #[derive(Serialize, Deserialize)]
-        struct Claims {
-            sub: i32,
-            exp: u64,
+    async fn dummy_hash(&self) -> Result<&str, AppError> {
+        self.dummy_hash
+            .get_or_try_init(|| async move {
+                let password = random_token(32);
+                self.password_hasher.hash(&password).await
+            })
+            .await
+            .map(String::as_str)
+    }
+
+    async fn issue_tokens(&self, user_id: i32) -> Result<AuthResponse, AppError> {
+        let family_id = random_token(32);
+        let token = self.generate_token(user_id, &family_id).await?;
+        let refresh_token = self.issue_refresh_token(user_id, &family_id).await?;
+
+        Ok(AuthResponse {
+            token,
+            refresh_token: Some(refresh_token),
+        })
+    }
+
+    /// Stores a new refresh token in `family_id` and returns the plaintext value.
+    async fn issue_refresh_token(&self, user_id: i32, family_id: &str) -> Result<String, AppError> {
+        let refresh_token = random_token(64);
+
+        self.refresh_token_repository
+            .create_token(
+                user_id,
+                &sha256_hex(&refresh_token),
+                family_id,
+                OffsetDateTime::now_utc() + REFRESH_TOKEN_TTL,
+            )
+            .await?;
+
+        Ok(refresh_token)
+    }
+
+    /// Finds the local user for an external identity, linking or creating one on first login.
+    async fn resolve_identity(&self, identity: &ExternalIdentity) -> Result<User, AppError> {
+        match self
+            .identity_repository
+            .get_identity(&identity.provider, &identity.subject)
+            .await
+        {
+            Ok(linked) => return self.user_repository.get_user_by_id(linked.user_id).await,
+            Err(AppError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+
+        let email = identity.email.as_deref().ok_or_else(|| {
+            AppError::BadRequest("Identity provider did not return an email address".to_string())
+        })?;
+
+        let user = match self.user_repository.get_user_by_email(email).await {
+            // Only a verified email proves the caller owns the existing account.
+            Ok(user) if identity.email_verified => user,
+            Ok(_) => return Err(AppError::Unauthorized),
+            Err(AppError::NotFound) => {
+                let preferred = identity
+                    .username
+                    .as_deref()
+                    .unwrap_or_else(|| email.split('@').next().unwrap_or(email));
+                let username = self.available_username(preferred).await?;
+                self.user_repository
+                    .create_user(&username, Some(email), None)
+                    .await?
+            }
+            Err(e) => return Err(e),
+        };
+
+        // The provider has already confirmed the address, so no link needs to be sent.
+        if identity.email_verified {
+            self.user_repository
+                .mark_email_verified(user.id, email)
+                .await?;
+        }
+
+        self.identity_repository
+            .create_identity(&identity.provider, &identity.subject, user.id)
+            .await?;
+
+        Ok(user)
+    }
+
+    fn ensure_email_verified(&self, user: &User) -> Result<(), AppError> {
+        if self.require_email_verification
+            && user.email.is_some()
+            && user.email_verified_at.is_none()
+        {
+            return Err(AppError::EmailNotVerified);
+        }
+        Ok(())
+    }
+
+    async fn available_username(&self, preferred: &str) -> Result<String, AppError> {
+        let base: String = preferred
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            .take(40)
+            .collect();
+        let base = if base.is_empty() {
+            "user".to_string()
+        } else {
+            base
+        };
+
+        match self.user_repository.get_user_by_username(&base).await {
+            Err(AppError::NotFound) => Ok(base),
+            Ok(_) => Ok(format!("{}-{}", base, random_token(6).to_lowercase())),
+            Err(e) => Err(e),
         }
+    }
 
-        let expiration = SystemTime::now()
-            .checked_add(Duration::from_secs(60 * 60))
-            .expect("Invalid timestamp")
+    /// Signs an access token for the session identified by the refresh token `family_id`.
+    async fn generate_token(&self, user_id: i32, family_id: &str) -> Result<String, AppError> {
+        let user = self.user_repository.get_user_by_id(user_id).await?;
+        let roles = self.permission_service.get_user_roles(user_id).await?;
+        let issued_at = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards")
             .as_secs();
 
         let claims = Claims {
+            iss: self.access_token_config.issuer.clone(),
             sub: user_id,
-            exp: expiration,
+            aud: self.access_token_config.audience.clone(),
+            exp: issued_at + self.access_token_config.ttl_seconds,
+            nbf: issued_at,
+            iat: issued_at,
+            jti: random_token(32),
+            sid: family_id.to_string(),
+            username: user.username,
+            roles,
+        };
+
+        self.jwt_keys.encode(&claims)
+    }
+
+    fn generate_mfa_token(&self, user_id: i32) -> Result<String, AppError> {
+        let issued_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+
+        let claims = MfaClaims {
+            sub: user_id,
+            aud: MFA_AUDIENCE.to_string(),
+            exp: issued_at + MFA_TOKEN_TTL.as_secs(),
+            iat: issued_at,
+            jti: random_token(32),
         };
 
         encode(
@@ -54,33 +283,320 @@ impl AuthServiceImpl {
         )
         .map_err(|_| AppError::InternalServerError)
     }
+
+    async fn verify_token(&self, token: &str) -> Result<Claims, AppError> {
+        let mut validation = Validation::default();
+        validation.set_issuer(&[&self.access_token_config.issuer]);
+        validation.set_audience(&[&self.access_token_config.audience]);
+        validation.validate_nbf = true;
+        validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud"]);
+        let claims: Claims = self.jwt_keys.decode(token, validation)?;
+
+        if self
+            .revocation_service
+            .is_revoked(&claims.jti, claims.sub, unix_time(claims.iat)?)
+            .await?
+        {
+            return Err(AppError::Unauthorized);
+        }
+
+        Ok(claims)
+    }
 }
 
 #[async_trait]
 impl AuthService for AuthServiceImpl {
     async fn register(&self, req: RegisterRequest) -> Result<AuthResponse, AppError> {
-        let password_hash = hash(req.password, 10).map_err(|_| AppError::InternalServerError)?;
+        self.password_policy
+            .validate(&req.username, Some(&req.email), &req.password)
+            .await?;
+
+        let password_hash = self.password_hasher.hash(&req.password).await?;
 
         let user = self
             .user_repository
-            .create_user(&req.username, &req.email, &password_hash)
+            .create_user(&req.username, Some(&req.email), Some(&password_hash))
             .await?;
 
-        let token = self.generate_token(user.id)?;
-        Ok(AuthResponse { token })
+        // The account exists either way; a failed send can be retried from the resend form.
+        if let Err(e) = self
+            .email_verification_service
+            .send_verification(&user)
+            .await
+        {
+            tracing::warn!(
+                "failed to send verification email to user {}: {}",
+                user.id,
+                e
+            );
+        }
+        self.ensure_email_verified(&user)?;
+
+        self.issue_tokens(user.id).await
     }
 
-    async fn login(&self, req: LoginRequest) -> Result<AuthResponse, AppError> {
-        let user = self
+    async fn login(&self, req: LoginRequest, client_ip: IpAddr) -> Result<LoginResult, AppError> {
+        self.login_throttle_service
+            .check(&req.username, client_ip)
+            .await?;
+
+        let user = match self
             .user_repository
             .get_user_by_username(&req.username)
+            .await
+        {
+            Ok(user) => Some(user),
+            Err(AppError::NotFound) => None,
+            Err(e) => return Err(e),
+        };
+
+        // Unknown usernames and accounts provisioned from an identity provider are
+        // checked against a dummy hash, so neither the response nor its timing
+        // reveals whether the account exists.
+        let stored_hash = user.as_ref().and_then(|u| u.password_hash.clone());
+        let checked_hash = match stored_hash.as_deref() {
+            Some(password_hash) => password_hash,
+            None => self.dummy_hash().await?,
+        };
+        let verified = self
+            .password_hasher
+            .verify(&req.password, checked_hash)
             .await?;
 
-        if verify(&req.password, &user.password_hash).map_err(|_| AppError::InternalServerError)? {
-            let token = self.generate_token(user.id)?;
-            Ok(AuthResponse { token })
-        } else {
-            Err(AppError::Unauthorized)
+        let (user, password_hash) = match (user, stored_hash) {
+            (Some(user), Some(password_hash)) if verified => (user, password_hash),
+            _ => {
+                self.login_throttle_service
+                    .record_failure(&req.username, client_ip)
+                    .await?;
+                return Err(AppError::Unauthorized);
+            }
+        };
+        self.ensure_email_verified(&user)?;
+
+        // The plaintext is only available here, so outdated hashes are upgraded on login.
+        if self.password_hasher.needs_rehash(&password_hash) {
+            let upgraded = self.password_hasher.hash(&req.password).await?;
+            self.user_repository
+                .update_password_hash(user.id, &upgraded)
+                .await?;
         }
+
+        if self.totp_service.is_enabled(user.id).await? {
+            return Ok(LoginResult::MfaRequired(MfaChallenge {
+                mfa_token: self.generate_mfa_token(user.id)?,
+            }));
+        }
+
+        self.login_throttle_service
+            .record_success(&user.username)
+            .await?;
+        Ok(LoginResult::Authenticated(
+            self.issue_tokens(user.id).await?,
+        ))
     }
+
+    async fn verify_mfa(
+        &self,
+        mfa_token: &str,
+        code: &str,
+        client_ip: IpAddr,
+    ) -> Result<AuthResponse, AppError> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[MFA_AUDIENCE]);
+        validation.set_required_spec_claims(&["exp", "aud"]);
+
+        let claims = decode::<MfaClaims>(
+            mfa_token,
+            &DecodingKey::from_secret(self.jwt_secret.as_ref()),
+            &validation,
+        )
+        .map_err(|_| AppError::Unauthorized)?
+        .claims;
+
+        if self
+            .revocation_service
+            .is_revoked(&claims.jti, claims.sub, unix_time(claims.iat)?)
+            .await?
+        {
+            return Err(AppError::Unauthorized);
+        }
+
+        // Second factor guesses count against the same limits as password guesses.
+        let user = self.user_repository.get_user_by_id(claims.sub).await?;
+        self.login_throttle_service
+            .check(&user.username, client_ip)
+            .await?;
+
+        if !self.totp_service.verify(claims.sub, code).await? {
+            self.login_throttle_service
+                .record_failure(&user.username, client_ip)
+                .await?;
+            return Err(AppError::Unauthorized);
+        }
+        self.login_throttle_service
+            .record_success(&user.username)
+            .await?;
+
+        // The pending token has served its purpose and must not start a second session.
+        self.revocation_service
+            .revoke_token(&claims.jti, claims.sub, unix_time(claims.exp)?)
+            .await?;
+
+        self.issue_tokens(claims.sub).await
+    }
+
+    async fn refresh(&self, req: RefreshRequest) -> Result<AuthResponse, AppError> {
+        let stored = match self
+            .refresh_token_repository
+            .get_token_by_hash(&sha256_hex(&req.refresh_token))
+            .await
+        {
+            Ok(stored) => stored,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+
+        if stored.revoked_at.is_some() {
+            return Err(AppError::Unauthorized);
+        }
+
+        // A refresh token that was already rotated is being replayed, so the
+        // whole family is considered compromised.
+        if stored.used_at.is_some() || !self.refresh_token_repository.mark_used(stored.id).await? {
+            self.refresh_token_repository
+                .revoke_family(&stored.family_id)
+                .await?;
+            return Err(AppError::Unauthorized);
+        }
+
+        if stored.expires_at <= OffsetDateTime::now_utc() {
+            return Err(AppError::Unauthorized);
+        }
+
+        let token = self
+            .generate_token(stored.user_id, &stored.family_id)
+            .await?;
+        let refresh_token = self
+            .issue_refresh_token(stored.user_id, &stored.family_id)
+            .await?;
+
+        Ok(AuthResponse {
+            token,
+            refresh_token: Some(refresh_token),
+        })
+    }
+
+    async fn login_with_identity(
+        &self,
+        identity: ExternalIdentity,
+    ) -> Result<AuthResponse, AppError> {
+        let user = self.resolve_identity(&identity).await?;
+        // Re-read so an address verified just now by the provider is taken into account.
+        let user = self.user_repository.get_user_by_id(user.id).await?;
+        self.ensure_email_verified(&user)?;
+        self.issue_tokens(user.id).await
+    }
+
+    async fn login_with_wallet(&self, address: &str) -> Result<AuthResponse, AppError> {
+        let user_id = match self.wallet_repository.get_wallet_by_address(address).await {
+            Ok(wallet) => wallet.user_id,
+            Err(AppError::NotFound) => {
+                // e.g. "0x1a2b3c4d" for 0x1a2b3c4d...
+                let username = self.available_username(&address[..10]).await?;
+                let user = self
+                    .user_repository
+                    .create_user(&username, None, None)
+                    .await?;
+                match self
+                    .wallet_repository
+                    .create_wallet(user.id, address)
+                    .await?
+                {
+                    Some(wallet) => wallet.user_id,
+                    None => {
+                        // A concurrent first login claimed the address; use its account.
+                        self.user_repository.delete_user(user.id).await?;
+                        self.wallet_repository
+                            .get_wallet_by_address(address)
+                            .await?
+                            .user_id
+                    }
+                }
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.issue_tokens(user_id).await
+    }
+
+    async fn link_wallet(&self, user_id: i32, address: &str) -> Result<(), AppError> {
+        let wallet = match self.wallet_repository.get_wallet_by_address(address).await {
+            Ok(wallet) => wallet,
+            Err(AppError::NotFound) => {
+                match self
+                    .wallet_repository
+                    .create_wallet(user_id, address)
+                    .await?
+                {
+                    Some(wallet) => wallet,
+                    // Linked by a concurrent request in the meantime.
+                    None => {
+                        self.wallet_repository
+                            .get_wallet_by_address(address)
+                            .await?
+                    }
+                }
+            }
+            Err(e) => return Err(e),
+        };
+
+        if wallet.user_id != user_id {
+            return Err(AppError::BadRequest(
+                "Wallet address is already linked to another account".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn login_with_passkey(&self, user_id: i32) -> Result<AuthResponse, AppError> {
+        let user = self.user_repository.get_user_by_id(user_id).await?;
+        self.ensure_email_verified(&user)?;
+        self.issue_tokens(user.id).await
+    }
+
+    async fn authenticate(&self, token: &str) -> Result<User, AppError> {
+        let claims = self.verify_token(token).await?;
+
+        // A token for a deleted user must not authenticate anyone.
+        match self.user_repository.get_user_by_id(claims.sub).await {
+            Ok(user) => Ok(user),
+            Err(AppError::NotFound) => Err(AppError::Unauthorized),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn logout(&self, token: &str) -> Result<(), AppError> {
+        let claims = self.verify_token(token).await?;
+
+        self.revocation_service
+            .revoke_token(&claims.jti, claims.sub, unix_time(claims.exp)?)
+            .await?;
+        // Otherwise the session's refresh token could mint a new access token.
+        self.refresh_token_repository
+            .revoke_family(&claims.sid)
+            .await
+    }
+
+    async fn logout_all(&self, user_id: i32) -> Result<(), AppError> {
+        self.refresh_token_repository
+            .revoke_all_for_user(user_id)
+            .await?;
+        self.oauth2_repository.revoke_all_for_user(user_id).await?;
+        self.revocation_service.revoke_all_for_user(user_id).await
+    }
+}
+
+fn unix_time(seconds: u64) -> Result<OffsetDateTime, AppError> {
+    OffsetDateTime::from_unix_timestamp(seconds as i64).map_err(|_| AppError::Unauthorized)
 }
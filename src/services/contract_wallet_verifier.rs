@@ -0,0 +1,202 @@
+use crate::error::AppError;
+use async_trait::async_trait;
+use ethers::abi::{encode, Token};
+use ethers::providers::{Http, Middleware, Provider, ProviderError, RpcError};
+use ethers::types::{transaction::eip2718::TypedTransaction, Address, TransactionRequest};
+use std::collections::HashMap;
+
+/// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`, which is also the
+/// value a contract returns when it accepts the signature.
+const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// Verifies signatures made by smart-contract wallets (EIP-1271).
+#[async_trait]
+pub trait ContractWalletVerifier: Send + Sync {
+    async fn is_valid_signature(
+        &self,
+        chain_id: u64,
+        address: Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<bool, AppError>;
+}
+
+pub struct ContractWalletVerifierImpl {
+    providers: HashMap<u64, Provider<Http>>,
+}
+
+impl ContractWalletVerifierImpl {
+    /// Takes the JSON-RPC endpoint to use for each chain ID. Chains without an
+    /// endpoint only accept EOA signatures.
+    pub fn new(rpc_urls: HashMap<u64, String>) -> Result<Self, url::ParseError> {
+        let providers = rpc_urls
+            .into_iter()
+            .map(|(chain_id, url)| Ok((chain_id, Provider::<Http>::try_from(url.as_str())?)))
+            .collect::<Result<_, url::ParseError>>()?;
+
+        Ok(Self { providers })
+    }
+}
+
+#[async_trait]
+impl ContractWalletVerifier for ContractWalletVerifierImpl {
+    async fn is_valid_signature(
+        &self,
+        chain_id: u64,
+        address: Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<bool, AppError> {
+        let Some(provider) = self.providers.get(&chain_id) else {
+            return Ok(false);
+        };
+
+        let calldata = [
+            EIP1271_MAGIC_VALUE.as_slice(),
+            &encode(&[
+                Token::FixedBytes(hash.to_vec()),
+                Token::Bytes(signature.to_vec()),
+            ]),
+        ]
+        .concat();
+        let tx: TypedTransaction = TransactionRequest::new().to(address).data(calldata).into();
+
+        // Plain accounts have no code and contracts reject unknown signatures by
+        // reverting, so a revert simply means the signature is not valid. Anything
+        // else means we could not find out.
+        let result = match provider.call(&tx, None).await {
+            Ok(result) => result,
+            Err(e) if is_revert(&e) => return Ok(false),
+            Err(e) => {
+                tracing::warn!("EIP-1271 check on chain {} failed: {}", chain_id, e);
+                return Err(AppError::InternalServerError);
+            }
+        };
+
+        Ok(result.len() >= 4 && result[..4] == EIP1271_MAGIC_VALUE)
+    }
+}
+
+fn is_revert(err: &ProviderError) -> bool {
+    // Nodes report reverts with code 3, or as "execution reverted" in older versions.
+    err.as_error_response()
+        .is_some_and(|response| response.code == 3 || response.message.contains("revert"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    const CHAIN_ID: u64 = 1;
+
+    /// Starts a JSON-RPC endpoint that answers every request with `reply`, which holds
+    /// either a `result` or an `error` member.
+    async fn rpc_server(reply: Value) -> String {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| {
+                let mut response = json!({ "jsonrpc": "2.0", "id": request["id"] });
+                response
+                    .as_object_mut()
+                    .unwrap()
+                    .extend(reply.as_object().unwrap().clone());
+                async move { Json(response) }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    async fn verifier(reply: Value) -> ContractWalletVerifierImpl {
+        let url = rpc_server(reply).await;
+        ContractWalletVerifierImpl::new(HashMap::from([(CHAIN_ID, url)])).unwrap()
+    }
+
+    async fn check(verifier: &ContractWalletVerifierImpl, chain_id: u64) -> Result<bool, AppError> {
+        verifier
+            .is_valid_signature(
+                chain_id,
+                Address::repeat_byte(0x11),
+                [0x22; 32],
+                &[0x33; 65],
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn accepts_magic_value() {
+        let magic = format!("0x1626ba7e{}", "0".repeat(56));
+        let verifier = verifier(json!({ "result": magic })).await;
+
+        assert!(check(&verifier, CHAIN_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_other_return_values() {
+        let other = format!("0x{}", "0".repeat(64));
+        let verifier = verifier(json!({ "result": other })).await;
+
+        assert!(!check(&verifier, CHAIN_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_accounts_without_code() {
+        let verifier = verifier(json!({ "result": "0x" })).await;
+
+        assert!(!check(&verifier, CHAIN_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_reverts() {
+        let verifier = verifier(json!({
+            "error": { "code": 3, "message": "execution reverted", "data": "0x" }
+        }))
+        .await;
+
+        assert!(!check(&verifier, CHAIN_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn fails_when_node_errors() {
+        let verifier = verifier(json!({
+            "error": { "code": -32603, "message": "internal error" }
+        }))
+        .await;
+
+        assert!(matches!(
+            check(&verifier, CHAIN_ID).await,
+            Err(AppError::InternalServerError)
+        ));
+    }
+
+    #[tokio::test]
+    async fn fails_when_node_is_unreachable() {
+        let verifier = ContractWalletVerifierImpl::new(HashMap::from([(
+            CHAIN_ID,
+            "http://127.0.0.1:1".to_string(),
+        )]))
+        .unwrap();
+
+        assert!(check(&verifier, CHAIN_ID).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_chains_without_endpoint() {
+        let verifier = verifier(json!({ "result": "0x" })).await;
+
+        assert!(!check(&verifier, 137).await.unwrap());
+    }
+
+    #[test]
+    fn rejects_invalid_rpc_urls() {
+        assert!(ContractWalletVerifierImpl::new(HashMap::from([(
+            CHAIN_ID,
+            "not a url".to_string()
+        )]))
+        .is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use crate::error::AppError;
+use crate::models::User;
+use crate::repositories::UserRepository;
+use crate::services::mailer::{Email, Mailer};
+use crate::services::RateLimitService;
+use crate::utils::network::client_network;
+use async_trait::async_trait;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const VERIFICATION_TOKEN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Keeps verification links from being accepted as access tokens and vice versa.
+const VERIFICATION_AUDIENCE: &str = "email-verification";
+/// Links one address may be sent per window, so it cannot be flooded with mail.
+const RESENDS_PER_ADDRESS: i32 = 3;
+/// Resends one network may ask for per window, whichever addresses it names.
+const RESENDS_PER_NETWORK: i32 = 10;
+const RESEND_WINDOW: time::Duration = time::Duration::hours(1);
+
+#[derive(Serialize, Deserialize)]
+struct VerificationClaims {
+    sub: i32,
+    /// The address being verified, so a link stops working once the email changes.
+    email: String,
+    aud: String,
+    exp: u64,
+}
+
+#[async_trait]
+pub trait EmailVerificationService: Send + Sync {
+    async fn send_verification(&self, user: &User) -> Result<(), AppError>;
+    async fn verify(&self, token: &str) -> Result<(), AppError>;
+    /// Sends a fresh link to `email` if it belongs to an unverified account, and does
+    /// nothing otherwise so callers cannot probe which addresses are registered.
+    async fn resend(&self, email: &str, client_ip: IpAddr) -> Result<(), AppError>;
+}
+
+pub struct EmailVerificationServiceImpl {
+    user_repository: Arc<dyn UserRepository>,
+    mailer: Arc<dyn Mailer>,
+    rate_limit_service: Arc<dyn RateLimitService>,
+    jwt_secret: String,
+    app_base_url: String,
+}
+
+impl EmailVerificationServiceImpl {
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        mailer: Arc<dyn Mailer>,
+        rate_limit_service: Arc<dyn RateLimitService>,
+        jwt_secret: String,
+        app_base_url: String,
+    ) -> Self {
+        Self {
+            user_repository,
+            mailer,
+            rate_limit_service,
+            jwt_secret,
+            app_base_url,
+        }
+    }
+
+    fn generate_token(&self, user_id: i32, email: &str) -> Result<String, AppError> {
+        let expiration = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs()
+            + VERIFICATION_TOKEN_TTL.as_secs();
+
+        let claims = VerificationClaims {
+            sub: user_id,
+            email: email.to_string(),
+            aud: VERIFICATION_AUDIENCE.to_string(),
+            exp: expiration,
+        };
+
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.jwt_secret.as_ref()),
+        )
+        .map_err(|_| AppError::InternalServerError)
+    }
+}
+
+#[async_trait]
+impl EmailVerificationService for EmailVerificationServiceImpl {
+    async fn send_verification(&self, user: &User) -> Result<(), AppError> {
+        let Some(email) = user.email.as_deref() else {
+            return Ok(());
+        };
+        if user.email_verified_at.is_some() {
+            return Ok(());
+        }
+
+        let token = self.generate_token(user.id, email)?;
+        let link = format!(
+            "{}/verify-email?token={}",
+            self.app_base_url.trim_end_matches('/'),
+            token
+        );
+
+        self.mailer
+            .send(Email {
+                to: email.to_string(),
+                subject: "Verify your email address".to_string(),
+                body: format!(
+                    "Hi {},\n\nConfirm your email address by opening the link below. It expires in 24 hours.\n\n{}\n",
+                    user.username, link
+                ),
+            })
+            .await
+    }
+
+    async fn verify(&self, token: &str) -> Result<(), AppError> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[VERIFICATION_AUDIENCE]);
+        validation.set_required_spec_claims(&["exp", "aud"]);
+
+        let claims = decode::<VerificationClaims>(
+            token,
+            &DecodingKey::from_secret(self.jwt_secret.as_ref()),
+            &validation,
+        )
+        .map_err(|_| AppError::BadRequest("Invalid or expired verification link".to_string()))?
+        .claims;
+
+        if !self
+            .user_repository
+            .mark_email_verified(claims.sub, &claims.email)
+            .await?
+        {
+            return Err(AppError::BadRequest(
+                "Invalid or expired verification link".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn resend(&self, email: &str, client_ip: IpAddr) -> Result<(), AppError> {
+        // Both limits count every request, registered address or not, so hitting one
+        // says nothing about whether the account exists.
+        self.rate_limit_service
+            .hit(
+                &format!("verification_resend:ip:{}", client_network(client_ip)),
+                RESENDS_PER_NETWORK,
+                RESEND_WINDOW,
+            )
+            .await?;
+        self.rate_limit_service
+            .hit(
+                &format!("verification_resend:email:{}", email.trim().to_lowercase()),
+                RESENDS_PER_ADDRESS,
+                RESEND_WINDOW,
+            )
+            .await?;
+
+        match self.user_repository.get_user_by_email(email).await {
+            Ok(user) => self.send_verification(&user).await,
+            Err(AppError::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{InMemoryMailer, InMemoryRateLimiter};
+    use std::net::Ipv4Addr;
+    use std::sync::Mutex;
+    use time::OffsetDateTime;
+
+    const SECRET: &str = "test-secret";
+    const CLIENT_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7));
+
+    struct FakeUserRepository {
+        users: Mutex<Vec<User>>,
+    }
+
+    impl FakeUserRepository {
+        fn find(&self, matches: impl Fn(&User) -> bool) -> Result<User, AppError> {
+            let users = self.users.lock().unwrap();
+            users
+                .iter()
+                .find(|u| matches(u))
+                .cloned()
+                .ok_or(AppError::NotFound)
+        }
+    }
+
+    #[async_trait]
+    impl UserRepository for FakeUserRepository {
+        async fn create_user(
+            &self,
+            _username: &str,
+            _email: Option<&str>,
+            _password_hash: Option<&str>,
+        ) -> Result<User, AppError> {
+            Err(AppError::InternalServerError)
+        }
+
+        async fn get_user_by_username(&self, username: &str) -> Result<User, AppError> {
+            self.find(|u| u.username == username)
+        }
+
+        async fn get_user_by_email(&self, email: &str) -> Result<User, AppError> {
+            self.find(|u| u.email.as_deref() == Some(email))
+        }
+
+        async fn get_user_by_id(&self, id: i32) -> Result<User, AppError> {
+            self.find(|u| u.id == id)
+        }
+
+        async fn get_all_users(&self) -> Result<Vec<User>, AppError> {
+            Ok(self.users.lock().unwrap().clone())
+        }
+
+        async fn update_password_hash(
+            &self,
+            _id: i32,
+            _password_hash: &str,
+        ) -> Result<(), AppError> {
+            Err(AppError::InternalServerError)
+        }
+
+        async fn mark_email_verified(&self, id: i32, email: &str) -> Result<bool, AppError> {
+            let mut users = self.users.lock().unwrap();
+            match users
+                .iter_mut()
+                .find(|u| u.id == id && u.email.as_deref() == Some(email))
+            {
+                Some(user) => {
+                    user.email_verified_at
+                        .get_or_insert_with(OffsetDateTime::now_utc);
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        async fn delete_user(&self, id: i32) -> Result<(), AppError> {
+            self.users.lock().unwrap().retain(|u| u.id != id);
+            Ok(())
+        }
+    }
+
+    fn user(id: i32, email: &str) -> User {
+        User {
+            id,
+            username: format!("user{id}"),
+            email: Some(email.to_string()),
+            password_hash: None,
+            email_verified_at: None,
+        }
+    }
+
+    fn setup(
+        users: Vec<User>,
+    ) -> (
+        Arc<FakeUserRepository>,
+        Arc<InMemoryMailer>,
+        EmailVerificationServiceImpl,
+    ) {
+        let repository = Arc::new(FakeUserRepository {
+            users: Mutex::new(users),
+        });
+        let mailer = Arc::new(InMemoryMailer::new());
+        let service = EmailVerificationServiceImpl::new(
+            repository.clone(),
+            mailer.clone(),
+            Arc::new(InMemoryRateLimiter::new()),
+            SECRET.to_string(),
+            "https://auth.example.com/".to_string(),
+        );
+        (repository, mailer, service)
+    }
+
+    fn token_from(email: &Email) -> String {
+        let (_, rest) = email.body.split_once("/verify-email?token=").unwrap();
+        rest.split_whitespace().next().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn sends_verification_link_to_new_user() {
+        let alice = user(1, "alice@example.com");
+        let (_, mailer, service) = setup(vec![alice.clone()]);
+
+        service.send_verification(&alice).await.unwrap();
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "alice@example.com");
+        assert!(sent[0]
+            .body
+            .contains("https://auth.example.com/verify-email?token="));
+    }
+
+    #[tokio::test]
+    async fn skips_already_verified_users() {
+        let mut alice = user(1, "alice@example.com");
+        alice.email_verified_at = Some(OffsetDateTime::now_utc());
+        let (_, mailer, service) = setup(vec![alice.clone()]);
+
+        service.send_verification(&alice).await.unwrap();
+
+        assert!(mailer.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn link_verifies_email() {
+        let alice = user(1, "alice@example.com");
+        let (repository, mailer, service) = setup(vec![alice.clone()]);
+        service.send_verification(&alice).await.unwrap();
+
+        service
+            .verify(&token_from(&mailer.sent()[0]))
+            .await
+            .unwrap();
+
+        let alice = repository.get_user_by_id(1).await.unwrap();
+        assert!(alice.email_verified_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn link_stops_working_once_email_changes() {
+        let alice = user(1, "alice@example.com");
+        let (repository, mailer, service) = setup(vec![alice.clone()]);
+        service.send_verification(&alice).await.unwrap();
+        repository.users.lock().unwrap()[0].email = Some("alice@example.org".to_string());
+
+        let result = service.verify(&token_from(&mailer.sent()[0])).await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        let alice = repository.get_user_by_id(1).await.unwrap();
+        assert!(alice.email_verified_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_links() {
+        let (_, _, service) = setup(vec![user(1, "alice@example.com")]);
+
+        let result = service.verify("not-a-token").await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn resend_mails_unverified_accounts_only() {
+        let mut bob = user(2, "bob@example.com");
+        bob.email_verified_at = Some(OffsetDateTime::now_utc());
+        let (_, mailer, service) = setup(vec![user(1, "alice@example.com"), bob]);
+
+        service
+            .resend("alice@example.com", CLIENT_IP)
+            .await
+            .unwrap();
+        service.resend("bob@example.com", CLIENT_IP).await.unwrap();
+        service
+            .resend("nobody@example.com", CLIENT_IP)
+            .await
+            .unwrap();
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "alice@example.com");
+    }
+
+    #[tokio::test]
+    async fn resend_is_limited_per_address() {
+        let (_, mailer, service) = setup(vec![user(1, "alice@example.com")]);
+        for i in 0..RESENDS_PER_ADDRESS {
+            let client_ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, i as u8));
+            service
+                .resend("alice@example.com", client_ip)
+                .await
+                .unwrap();
+        }
+
+        let result = service.resend("Alice@example.com", CLIENT_IP).await;
+
+        assert!(matches!(result, Err(AppError::TooManyRequests { .. })));
+        assert_eq!(mailer.sent().len(), RESENDS_PER_ADDRESS as usize);
+    }
+
+    #[tokio::test]
+    async fn resend_is_limited_per_network() {
+        let (_, mailer, service) = setup(vec![user(1, "alice@example.com")]);
+        for i in 0..RESENDS_PER_NETWORK {
+            service
+                .resend(&format!("user{i}@example.com"), CLIENT_IP)
+                .await
+                .unwrap();
+        }
+
+        let result = service.resend("alice@example.com", CLIENT_IP).await;
+
+        assert!(matches!(result, Err(AppError::TooManyRequests { .. })));
+        assert!(mailer.sent().is_empty());
+    }
+}
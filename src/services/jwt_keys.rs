@@ -0,0 +1,235 @@
+use crate::config::JwtKeyConfig;
+use crate::error::AppError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+    EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::traits::PublicKeyParts;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io;
+use time::{Duration, OffsetDateTime};
+
+/// Used when no asymmetric keys are configured; never published.
+const SECRET_KEY_ID: &str = "secret";
+
+struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    /// Public half, or `None` for the shared secret.
+    jwk: Option<Jwk>,
+    active_from: OffsetDateTime,
+}
+
+/// The keys access tokens are signed and verified with.
+///
+/// The newest key whose `active_from` has passed signs new tokens. Older keys keep
+/// verifying until the grace period after their successor became active has run out.
+pub struct JwtKeys {
+    /// Ordered by `active_from`.
+    keys: Vec<SigningKey>,
+    grace_period: Duration,
+}
+
+impl JwtKeys {
+    pub fn new(
+        configs: &[JwtKeyConfig],
+        jwt_secret: &str,
+        grace_period_seconds: i64,
+    ) -> io::Result<Self> {
+        let mut keys = if configs.is_empty() {
+            vec![SigningKey {
+                kid: SECRET_KEY_ID.to_string(),
+                algorithm: Algorithm::HS256,
+                encoding_key: EncodingKey::from_secret(jwt_secret.as_ref()),
+                decoding_key: DecodingKey::from_secret(jwt_secret.as_ref()),
+                jwk: None,
+                active_from: OffsetDateTime::UNIX_EPOCH,
+            }]
+        } else {
+            configs
+                .iter()
+                .map(load_key)
+                .collect::<io::Result<Vec<_>>>()?
+        };
+        keys.sort_by_key(|key| key.active_from);
+
+        Ok(Self {
+            keys,
+            grace_period: Duration::seconds(grace_period_seconds),
+        })
+    }
+
+    fn current_key(&self) -> Result<&SigningKey, AppError> {
+        let now = OffsetDateTime::now_utc();
+        self.keys
+            .iter()
+            .rev()
+            .find(|key| key.active_from <= now)
+            .ok_or(AppError::InternalServerError)
+    }
+
+    /// Whether tokens signed with the key at `index` are still accepted at `now`.
+    fn is_trusted(&self, index: usize, now: OffsetDateTime) -> bool {
+        match self.keys.get(index + 1) {
+            Some(successor) => successor.active_from + self.grace_period > now,
+            None => true,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, AppError> {
+        let key = self.current_key()?;
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+
+        encode(&header, claims, &key.encoding_key).map_err(|_| AppError::InternalServerError)
+    }
+
+    /// Verifies `token` with the key named in its `kid` header. The algorithm is
+    /// always taken from the key, never from the token.
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        mut validation: Validation,
+    ) -> Result<T, AppError> {
+        let kid = decode_header(token)
+            .map_err(|_| AppError::Unauthorized)?
+            .kid
+            .ok_or(AppError::Unauthorized)?;
+        let now = OffsetDateTime::now_utc();
+        let (index, key) = self
+            .keys
+            .iter()
+            .enumerate()
+            .find(|(_, key)| key.kid == kid)
+            .ok_or(AppError::Unauthorized)?;
+        if key.active_from > now || !self.is_trusted(index, now) {
+            return Err(AppError::Unauthorized);
+        }
+
+        validation.algorithms = vec![key.algorithm];
+        decode::<T>(token, &key.decoding_key, &validation)
+            .map(|data| data.claims)
+            .map_err(|_| AppError::Unauthorized)
+    }
+
+    /// Public keys that are trusted now or scheduled to become active, so verifiers
+    /// can fetch a new key before the first token is signed with it.
+    pub fn jwks(&self) -> JwkSet {
+        let now = OffsetDateTime::now_utc();
+        JwkSet {
+            keys: self
+                .keys
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| self.is_trusted(*index, now))
+                .filter_map(|(_, key)| key.jwk.clone())
+                .collect(),
+        }
+    }
+}
+
+fn invalid_key(kid: &str, reason: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("JWT key {}: {}", kid, reason),
+    )
+}
+
+fn load_key(config: &JwtKeyConfig) -> io::Result<SigningKey> {
+    let pem = std::fs::read_to_string(&config.private_key_path)?;
+    let kid = config.kid.as_str();
+    let b64 = |bytes: &[u8]| URL_SAFE_NO_PAD.encode(bytes);
+
+    let (algorithm, key_algorithm, encoding_key, parameters) = match config.algorithm.as_str() {
+        "RS256" => {
+            let private_key = rsa::RsaPrivateKey::from_pkcs8_pem(&pem)
+                .or_else(|_| rsa::RsaPrivateKey::from_pkcs1_pem(&pem))
+                .map_err(|e| invalid_key(kid, e))?;
+            let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: b64(&private_key.n().to_bytes_be()),
+                e: b64(&private_key.e().to_bytes_be()),
+            });
+            let encoding_key =
+                EncodingKey::from_rsa_pem(pem.as_bytes()).map_err(|e| invalid_key(kid, e))?;
+            (
+                Algorithm::RS256,
+                KeyAlgorithm::RS256,
+                encoding_key,
+                parameters,
+            )
+        }
+        "ES256" => {
+            let private_key =
+                p256::SecretKey::from_pkcs8_pem(&pem).map_err(|e| invalid_key(kid, e))?;
+            let point = private_key.public_key().to_encoded_point(false);
+            let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                return Err(invalid_key(kid, "malformed P-256 public key"));
+            };
+            let parameters = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: EllipticCurve::P256,
+                x: b64(x),
+                y: b64(y),
+            });
+            let encoding_key =
+                EncodingKey::from_ec_pem(pem.as_bytes()).map_err(|e| invalid_key(kid, e))?;
+            (
+                Algorithm::ES256,
+                KeyAlgorithm::ES256,
+                encoding_key,
+                parameters,
+            )
+        }
+        "EdDSA" => {
+            let private_key =
+                ed25519_dalek::SigningKey::from_pkcs8_pem(&pem).map_err(|e| invalid_key(kid, e))?;
+            let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: b64(private_key.verifying_key().as_bytes()),
+            });
+            let encoding_key =
+                EncodingKey::from_ed_pem(pem.as_bytes()).map_err(|e| invalid_key(kid, e))?;
+            (
+                Algorithm::EdDSA,
+                KeyAlgorithm::EdDSA,
+                encoding_key,
+                parameters,
+            )
+        }
+        other => return Err(invalid_key(kid, format!("unsupported algorithm {}", other))),
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(config.kid.clone()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+    let decoding_key = DecodingKey::from_jwk(&jwk).map_err(|e| invalid_key(kid, e))?;
+
+    Ok(SigningKey {
+        kid: config.kid.clone(),
+        algorithm,
+        encoding_key,
+        decoding_key,
+        jwk: Some(jwk),
+        active_from: config.active_from,
+    })
+}
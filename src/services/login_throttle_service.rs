@@ -0,0 +1,103 @@
+use crate::error::AppError;
+use crate::repositories::LoginThrottleRepository;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// Failures older than this no longer count towards a lockout.
+const RESET_AFTER: Duration = Duration::minutes(15);
+const MAX_LOCKOUT: Duration = Duration::hours(1);
+const BASE_LOCKOUT: Duration = Duration::seconds(30);
+/// Failures allowed before a lockout starts, per account and per client address.
+/// The address limit is higher because users behind NAT share it.
+const FREE_ATTEMPTS_PER_USER: i32 = 5;
+const FREE_ATTEMPTS_PER_IP: i32 = 20;
+
+#[async_trait]
+pub trait LoginThrottleService: Send + Sync {
+    /// Fails with `AppError::TooManyRequests` while the account or address is locked out.
+    async fn check(&self, username: &str, client_ip: IpAddr) -> Result<(), AppError>;
+    async fn record_failure(&self, username: &str, client_ip: IpAddr) -> Result<(), AppError>;
+    async fn record_success(&self, username: &str) -> Result<(), AppError>;
+    /// Lifts a lockout on the account before it expires.
+    async fn unlock(&self, username: &str) -> Result<(), AppError>;
+}
+
+pub struct LoginThrottleServiceImpl {
+    login_throttle_repository: Arc<dyn LoginThrottleRepository>,
+}
+
+impl LoginThrottleServiceImpl {
+    pub fn new(login_throttle_repository: Arc<dyn LoginThrottleRepository>) -> Self {
+        Self {
+            login_throttle_repository,
+        }
+    }
+
+    async fn record(&self, key: &str, free_attempts: i32) -> Result<(), AppError> {
+        let throttle = self
+            .login_throttle_repository
+            .record_failure(key, RESET_AFTER.whole_seconds())
+            .await?;
+
+        if throttle.failures >= free_attempts {
+            // Doubles with every further failure, e.g. 30s, 1m, 2m, ... up to an hour.
+            let exponent = (throttle.failures - free_attempts).min(16) as u32;
+            let lockout = (BASE_LOCKOUT * 2_i32.pow(exponent)).min(MAX_LOCKOUT);
+            self.login_throttle_repository
+                .lock(key, OffsetDateTime::now_utc() + lockout)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn user_key(username: &str) -> String {
+    format!("user:{}", username.to_lowercase())
+}
+
+fn ip_key(client_ip: IpAddr) -> String {
+    format!("ip:{}", client_ip)
+}
+
+#[async_trait]
+impl LoginThrottleService for LoginThrottleServiceImpl {
+    async fn check(&self, username: &str, client_ip: IpAddr) -> Result<(), AppError> {
+        let keys = [user_key(username), ip_key(client_ip)];
+
+        match self
+            .login_throttle_repository
+            .get_locked_until(&keys)
+            .await?
+        {
+            Some(locked_until) => Err(AppError::TooManyRequests {
+                retry_after: (locked_until - OffsetDateTime::now_utc())
+                    .whole_seconds()
+                    .max(1) as u64,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    async fn record_failure(&self, username: &str, client_ip: IpAddr) -> Result<(), AppError> {
+        self.record(&user_key(username), FREE_ATTEMPTS_PER_USER)
+            .await?;
+        self.record(&ip_key(client_ip), FREE_ATTEMPTS_PER_IP).await
+    }
+
+    async fn record_success(&self, username: &str) -> Result<(), AppError> {
+        // The address counter is left to expire on its own, so an attacker cannot
+        // reset it by periodically signing in to an account of their own.
+        self.login_throttle_repository
+            .clear(&user_key(username))
+            .await
+    }
+
+    async fn unlock(&self, username: &str) -> Result<(), AppError> {
+        self.login_throttle_repository
+            .clear(&user_key(username))
+            .await
+    }
+}
@@ -0,0 +1,119 @@
+use crate::error::AppError;
+use crate::utils::crypto::random_token;
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+#[cfg(any(test, feature = "test_utils"))]
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(smtp_url: &str, from: Mailbox) -> Result<Self, lettre::transport::smtp::Error> {
+        Ok(Self {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(smtp_url)?.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let to: Mailbox = email
+            .to
+            .parse()
+            .map_err(|_| AppError::BadRequest("Invalid email address".to_string()))?;
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject)
+            .body(email.body)
+            .map_err(|_| AppError::InternalServerError)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+        Ok(())
+    }
+}
+
+/// Writes each email to its own file in `dir` instead of delivering it, for local development.
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+        let file_name = format!(
+            "{}-{}.eml",
+            OffsetDateTime::now_utc().unix_timestamp(),
+            random_token(8)
+        );
+        let contents = format!(
+            "To: {}\r\nSubject: {}\r\n\r\n{}\r\n",
+            email.to, email.subject, email.body
+        );
+
+        tokio::fs::write(self.dir.join(file_name), contents)
+            .await
+            .map_err(|_| AppError::InternalServerError)
+    }
+}
+
+/// Keeps sent emails in memory so tests can inspect them.
+#[cfg(any(test, feature = "test_utils"))]
+#[derive(Default)]
+pub struct InMemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+impl InMemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+#[async_trait]
+impl Mailer for InMemoryMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        self.sent.lock().unwrap().push(email);
+        Ok(())
+    }
+}
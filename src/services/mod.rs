@@ -1,11 +1,48 @@
+mod api_key_service;
 mod auth_service;
+mod contract_wallet_verifier;
+mod email_verification_service;
+mod jwt_keys;
+mod login_throttle_service;
+mod mailer;
+mod oauth2_server_service;
 mod oauth_service;
+mod oidc;
+mod password_hasher;
+mod password_policy;
+mod password_reset_service;
+mod permission_service;
 mod product_service;
+mod rate_limit_service;
+mod revocation_service;
 mod siwe_service;
+mod totp_service;
 mod user_service;
+mod webauthn_service;
 
+pub use api_key_service::{ApiKeyService, ApiKeyServiceImpl, API_KEY_PREFIX};
 pub use auth_service::{AuthService, AuthServiceImpl};
+pub use contract_wallet_verifier::{ContractWalletVerifier, ContractWalletVerifierImpl};
+pub use email_verification_service::{EmailVerificationService, EmailVerificationServiceImpl};
+pub use jwt_keys::JwtKeys;
+pub use login_throttle_service::{LoginThrottleService, LoginThrottleServiceImpl};
+#[cfg(any(test, feature = "test_utils"))]
+pub use mailer::InMemoryMailer;
+pub use mailer::{FileMailer, Mailer, SmtpMailer};
+pub use oauth2_server_service::{
+    OAuth2ServerService, OAuth2ServerServiceImpl, OAUTH2_ACCESS_TOKEN_PREFIX,
+};
 pub use oauth_service::{OAuthService, OAuthServiceImpl};
+pub use password_hasher::{PasswordHasher, PasswordHasherImpl};
+pub use password_policy::{PasswordPolicy, PasswordPolicyImpl};
+pub use password_reset_service::{PasswordResetService, PasswordResetServiceImpl};
+pub use permission_service::{PermissionService, PermissionServiceImpl};
 pub use product_service::{ProductService, ProductServiceImpl};
+#[cfg(any(test, feature = "test_utils"))]
+pub use rate_limit_service::InMemoryRateLimiter;
+pub use rate_limit_service::{RateLimitService, RateLimitServiceImpl};
+pub use revocation_service::{RevocationService, RevocationServiceImpl};
 pub use siwe_service::{SiweService, SiweServiceImpl};
+pub use totp_service::{TotpService, TotpServiceImpl};
 pub use user_service::{UserService, UserServiceImpl};
+pub use webauthn_service::{WebauthnService, WebauthnServiceImpl};
@@ -0,0 +1,884 @@
+use crate::error::AppError;
+use crate::models::oauth2::{
+    ACCESS_TOKEN_TYPE, DEVICE_CODE_APPROVED, DEVICE_CODE_DENIED, REFRESH_TOKEN_TYPE,
+};
+use crate::models::{
+    AuthorizationError, AuthorizationRequest, AuthorizeQuery, ClientCredentials,
+    DeviceAuthorizationResponse, IntrospectionResponse, NewOAuth2Client, OAuth2Client, OAuth2Token,
+    PendingDeviceAuthorization, TokenRequest, TokenResponse, User,
+};
+use crate::repositories::{OAuth2Repository, UserRepository};
+use crate::services::RateLimitService;
+use crate::utils::crypto::{constant_time_eq, random_token, sha256_hex};
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use url::Url;
+
+/// Marks a bearer credential as an OAuth2 access token rather than a JWT.
+pub const OAUTH2_ACCESS_TOKEN_PREFIX: &str = "oat_";
+
+const AUTHORIZATION_CODE_TTL: Duration = Duration::minutes(10);
+const ACCESS_TOKEN_TTL: Duration = Duration::hours(1);
+const REFRESH_TOKEN_TTL: Duration = Duration::days(30);
+const DEVICE_CODE_TTL: Duration = Duration::minutes(10);
+/// Seconds a device client waits between polls, and the penalty for polling faster.
+const DEVICE_POLL_INTERVAL: i32 = 5;
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Consonants only, so user codes are easy to type and never spell words (RFC 8628 section 6.1).
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LENGTH: usize = 8;
+/// Fresh user codes to try before giving up on one that no pending request uses.
+const USER_CODE_ATTEMPTS: usize = 5;
+/// Codes a user, or an address, may enter per window, so they cannot be guessed
+/// (RFC 8628 section 5.1). The address limit is higher because users behind NAT share it.
+const USER_CODE_ENTRIES_PER_USER: i32 = 10;
+const USER_CODE_ENTRIES_PER_IP: i32 = 50;
+const USER_CODE_WINDOW: Duration = Duration::minutes(15);
+
+#[async_trait]
+pub trait OAuth2ServerService: Send + Sync {
+    /// Registers a client, generating a secret for it if it is confidential.
+    async fn register_client(
+        &self,
+        name: &str,
+        redirect_uris: &[String],
+        scopes: &[String],
+        confidential: bool,
+    ) -> Result<NewOAuth2Client, AppError>;
+    async fn list_clients(&self) -> Result<Vec<OAuth2Client>, AppError>;
+    async fn delete_client(&self, client_id: &str) -> Result<(), AppError>;
+    async fn validate_authorization(
+        &self,
+        query: &AuthorizeQuery,
+    ) -> Result<AuthorizationRequest, AuthorizationError>;
+    /// Issues an authorization code and returns the URL that hands it to the client.
+    async fn approve(
+        &self,
+        user_id: i32,
+        request: &AuthorizationRequest,
+    ) -> Result<String, AppError>;
+    /// Returns the URL that tells the client the user declined.
+    fn deny(&self, request: &AuthorizationRequest) -> String;
+    async fn exchange(
+        &self,
+        credentials: Option<ClientCredentials>,
+        req: TokenRequest,
+    ) -> Result<TokenResponse, AppError>;
+    async fn introspect(
+        &self,
+        credentials: Option<ClientCredentials>,
+        token: &str,
+    ) -> Result<IntrospectionResponse, AppError>;
+    async fn revoke(
+        &self,
+        credentials: Option<ClientCredentials>,
+        token: &str,
+    ) -> Result<(), AppError>;
+    /// Resolves an access token presented to this service to the user who granted it.
+    async fn authenticate(&self, access_token: &str) -> Result<(User, OAuth2Token), AppError>;
+    /// Starts an RFC 8628 device flow, returning the codes the client shows the user.
+    async fn start_device_authorization(
+        &self,
+        credentials: Option<ClientCredentials>,
+        scope: Option<&str>,
+    ) -> Result<DeviceAuthorizationResponse, AppError>;
+    /// The request behind `user_code`, or `AppError::NotFound` if it is unknown or expired.
+    async fn pending_device_authorization(
+        &self,
+        user_id: i32,
+        client_ip: IpAddr,
+        user_code: &str,
+    ) -> Result<PendingDeviceAuthorization, AppError>;
+    async fn decide_device_authorization(
+        &self,
+        user_id: i32,
+        client_ip: IpAddr,
+        user_code: &str,
+        approved: bool,
+    ) -> Result<(), AppError>;
+}
+
+pub struct OAuth2ServerServiceImpl {
+    oauth2_repository: Arc<dyn OAuth2Repository>,
+    user_repository: Arc<dyn UserRepository>,
+    rate_limit_service: Arc<dyn RateLimitService>,
+    /// Page where users enter the code shown by a device.
+    verification_uri: String,
+}
+
+impl OAuth2ServerServiceImpl {
+    pub fn new(
+        oauth2_repository: Arc<dyn OAuth2Repository>,
+        user_repository: Arc<dyn UserRepository>,
+        rate_limit_service: Arc<dyn RateLimitService>,
+        app_base_url: &str,
+    ) -> Self {
+        Self {
+            oauth2_repository,
+            user_repository,
+            rate_limit_service,
+            verification_uri: format!("{}/device", app_base_url.trim_end_matches('/')),
+        }
+    }
+
+    /// Counts a user code entry, failing once the user or their address made too many.
+    async fn limit_user_code_entries(
+        &self,
+        user_id: i32,
+        client_ip: IpAddr,
+    ) -> Result<(), AppError> {
+        self.rate_limit_service
+            .hit(
+                &format!("user_code:user:{}", user_id),
+                USER_CODE_ENTRIES_PER_USER,
+                USER_CODE_WINDOW,
+            )
+            .await?;
+        self.rate_limit_service
+            .hit(
+                &format!("user_code:ip:{}", client_ip),
+                USER_CODE_ENTRIES_PER_IP,
+                USER_CODE_WINDOW,
+            )
+            .await
+    }
+
+    async fn authenticate_client(
+        &self,
+        credentials: Option<ClientCredentials>,
+    ) -> Result<OAuth2Client, AppError> {
+        let credentials = credentials
+            .ok_or_else(|| oauth2_error("invalid_client", "Client authentication is required"))?;
+        let client = match self
+            .oauth2_repository
+            .get_client(&credentials.client_id)
+            .await
+        {
+            Ok(client) => client,
+            Err(AppError::NotFound) => {
+                return Err(oauth2_error("invalid_client", "Unknown client"))
+            }
+            Err(e) => return Err(e),
+        };
+
+        let authenticated = match (&client.client_secret_hash, &credentials.client_secret) {
+            (Some(expected), Some(secret)) => constant_time_eq(expected, &sha256_hex(secret)),
+            (None, None) => true,
+            _ => false,
+        };
+        if !authenticated {
+            return Err(oauth2_error(
+                "invalid_client",
+                "Client authentication failed",
+            ));
+        }
+
+        Ok(client)
+    }
+
+    async fn issue_tokens(
+        &self,
+        client_id: &str,
+        user_id: Option<i32>,
+        scopes: &[String],
+        grant_id: &str,
+        with_refresh_token: bool,
+    ) -> Result<TokenResponse, AppError> {
+        let now = OffsetDateTime::now_utc();
+        let access_token = format!("{OAUTH2_ACCESS_TOKEN_PREFIX}{}", random_token(48));
+        self.oauth2_repository
+            .create_token(
+                &sha256_hex(&access_token),
+                ACCESS_TOKEN_TYPE,
+                client_id,
+                user_id,
+                scopes,
+                grant_id,
+                now + ACCESS_TOKEN_TTL,
+            )
+            .await?;
+
+        let refresh_token = if with_refresh_token {
+            let refresh_token = random_token(64);
+            self.oauth2_repository
+                .create_token(
+                    &sha256_hex(&refresh_token),
+                    REFRESH_TOKEN_TYPE,
+                    client_id,
+                    user_id,
+                    scopes,
+                    grant_id,
+                    now + REFRESH_TOKEN_TTL,
+                )
+                .await?;
+            Some(refresh_token)
+        } else {
+            None
+        };
+
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer",
+            expires_in: ACCESS_TOKEN_TTL.whole_seconds(),
+            refresh_token,
+            scope: scopes.join(" "),
+        })
+    }
+
+    async fn exchange_code(
+        &self,
+        client: OAuth2Client,
+        req: TokenRequest,
+    ) -> Result<TokenResponse, AppError> {
+        let code = req
+            .code
+            .ok_or_else(|| oauth2_error("invalid_request", "Missing code"))?;
+        let stored = match self
+            .oauth2_repository
+            .get_code_by_hash(&sha256_hex(&code))
+            .await
+        {
+            Ok(stored) if stored.client_id == client.client_id => stored,
+            Ok(_) | Err(AppError::NotFound) => return Err(invalid_grant()),
+            Err(e) => return Err(e),
+        };
+
+        // A code presented twice may have been intercepted, so whatever it was
+        // exchanged for the first time is revoked too.
+        if !self.oauth2_repository.mark_code_used(stored.id).await? {
+            self.oauth2_repository
+                .revoke_grant(&stored.grant_id)
+                .await?;
+            return Err(invalid_grant());
+        }
+        if stored.expires_at <= OffsetDateTime::now_utc() {
+            return Err(invalid_grant());
+        }
+        if req
+            .redirect_uri
+            .is_some_and(|redirect_uri| redirect_uri != stored.redirect_uri)
+        {
+            return Err(invalid_grant());
+        }
+        if let Some(challenge) = &stored.code_challenge {
+            let verifier = req
+                .code_verifier
+                .ok_or_else(|| oauth2_error("invalid_request", "Missing code_verifier"))?;
+            let computed = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+            if !constant_time_eq(&computed, challenge) {
+                return Err(invalid_grant());
+            }
+        }
+
+        self.issue_tokens(
+            &client.client_id,
+            Some(stored.user_id),
+            &stored.scopes,
+            &stored.grant_id,
+            true,
+        )
+        .await
+    }
+
+    async fn refresh(
+        &self,
+        client: OAuth2Client,
+        req: TokenRequest,
+    ) -> Result<TokenResponse, AppError> {
+        let refresh_token = req
+            .refresh_token
+            .ok_or_else(|| oauth2_error("invalid_request", "Missing refresh_token"))?;
+        let stored = match self
+            .oauth2_repository
+            .get_token_by_hash(&sha256_hex(&refresh_token))
+            .await
+        {
+            Ok(stored)
+                if stored.token_type == REFRESH_TOKEN_TYPE
+                    && stored.client_id == client.client_id =>
+            {
+                stored
+            }
+            Ok(_) | Err(AppError::NotFound) => return Err(invalid_grant()),
+            Err(e) => return Err(e),
+        };
+        if stored.expires_at <= OffsetDateTime::now_utc() {
+            return Err(invalid_grant());
+        }
+
+        // Refresh tokens rotate on use; seeing a rotated one again means it leaked.
+        if stored.revoked_at.is_some() || !self.oauth2_repository.revoke_token(stored.id).await? {
+            self.oauth2_repository
+                .revoke_grant(&stored.grant_id)
+                .await?;
+            return Err(invalid_grant());
+        }
+
+        let scopes = match parse_scopes(req.scope.as_deref()) {
+            requested if requested.is_empty() => stored.scopes.clone(),
+            requested if requested.iter().all(|scope| stored.scopes.contains(scope)) => requested,
+            _ => {
+                return Err(oauth2_error(
+                    "invalid_scope",
+                    "Scope exceeds the original grant",
+                ))
+            }
+        };
+
+        self.issue_tokens(
+            &client.client_id,
+            stored.user_id,
+            &scopes,
+            &stored.grant_id,
+            true,
+        )
+        .await
+    }
+
+    async fn client_credentials(
+        &self,
+        client: OAuth2Client,
+        req: TokenRequest,
+    ) -> Result<TokenResponse, AppError> {
+        if !client.is_confidential() {
+            return Err(oauth2_error(
+                "unauthorized_client",
+                "Public clients cannot use the client_credentials grant",
+            ));
+        }
+        let scopes = allowed_scopes(&client, req.scope.as_deref())
+            .ok_or_else(|| oauth2_error("invalid_scope", "Scope not allowed for this client"))?;
+
+        self.issue_tokens(&client.client_id, None, &scopes, &random_token(32), false)
+            .await
+    }
+
+    async fn exchange_device_code(
+        &self,
+        client: OAuth2Client,
+        req: TokenRequest,
+    ) -> Result<TokenResponse, AppError> {
+        let device_code = req
+            .device_code
+            .ok_or_else(|| oauth2_error("invalid_request", "Missing device_code"))?;
+        let stored = match self
+            .oauth2_repository
+            .get_device_code_by_hash(&sha256_hex(&device_code))
+            .await
+        {
+            Ok(stored) if stored.client_id == client.client_id && stored.used_at.is_none() => {
+                stored
+            }
+            Ok(_) | Err(AppError::NotFound) => return Err(invalid_grant()),
+            Err(e) => return Err(e),
+        };
+        let now = OffsetDateTime::now_utc();
+        if stored.expires_at <= now {
+            return Err(oauth2_error("expired_token", "The device code has expired"));
+        }
+
+        match stored.status.as_str() {
+            DEVICE_CODE_APPROVED => {}
+            DEVICE_CODE_DENIED => {
+                return Err(oauth2_error("access_denied", "The user denied the request"))
+            }
+            _ => {
+                // Clients polling faster than asked have to wait longer from now on.
+                let too_fast = stored.last_polled_at.is_some_and(|polled_at| {
+                    now - polled_at < Duration::seconds(stored.interval_seconds.into())
+                });
+                let interval = if too_fast {
+                    stored.interval_seconds + DEVICE_POLL_INTERVAL
+                } else {
+                    stored.interval_seconds
+                };
+                self.oauth2_repository
+                    .record_device_poll(stored.id, interval)
+                    .await?;
+                return Err(if too_fast {
+                    oauth2_error(
+                        "slow_down",
+                        format!("Poll at most every {interval} seconds"),
+                    )
+                } else {
+                    oauth2_error("authorization_pending", "The user has not responded yet")
+                });
+            }
+        }
+
+        if !self
+            .oauth2_repository
+            .mark_device_code_used(stored.id)
+            .await?
+        {
+            return Err(invalid_grant());
+        }
+        let user_id = stored.user_id.ok_or(AppError::InternalServerError)?;
+
+        self.issue_tokens(
+            &client.client_id,
+            Some(user_id),
+            &stored.scopes,
+            &random_token(32),
+            true,
+        )
+        .await
+    }
+}
+
+fn oauth2_error(error: &'static str, description: impl Into<String>) -> AppError {
+    AppError::OAuth2 {
+        error,
+        description: description.into(),
+    }
+}
+
+fn invalid_grant() -> AppError {
+    oauth2_error("invalid_grant", "Invalid, expired or revoked grant")
+}
+
+fn parse_scopes(scope: Option<&str>) -> Vec<String> {
+    scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Uppercases `user_code` and drops separators, so `bcdf-ghjk` matches `BCDFGHJK`.
+fn normalize_user_code(user_code: &str) -> String {
+    user_code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn format_user_code(user_code: &str) -> String {
+    let (first, second) = user_code.split_at(user_code.len() / 2);
+    format!("{first}-{second}")
+}
+
+fn random_user_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..USER_CODE_LENGTH)
+        .map(|_| USER_CODE_ALPHABET[rng.gen_range(0..USER_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+/// The requested scopes, or all of the client's when none are requested. `None` if
+/// any requested scope was not registered for the client.
+fn allowed_scopes(client: &OAuth2Client, scope: Option<&str>) -> Option<Vec<String>> {
+    let requested = parse_scopes(scope);
+    if requested.is_empty() {
+        return Some(client.scopes.clone());
+    }
+    requested
+        .iter()
+        .all(|scope| client.scopes.contains(scope))
+        .then_some(requested)
+}
+
+/// `redirect_uri` with `params` and the client's `state` added to its query.
+fn redirect_url(redirect_uri: &str, state: Option<&str>, params: &[(&str, &str)]) -> String {
+    let Ok(mut url) = Url::parse(redirect_uri) else {
+        return redirect_uri.to_string();
+    };
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in params {
+            query.append_pair(key, value);
+        }
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+    url.to_string()
+}
+
+#[async_trait]
+impl OAuth2ServerService for OAuth2ServerServiceImpl {
+    async fn register_client(
+        &self,
+        name: &str,
+        redirect_uris: &[String],
+        scopes: &[String],
+        confidential: bool,
+    ) -> Result<NewOAuth2Client, AppError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > 100 {
+            return Err(AppError::BadRequest(
+                "Name must be between 1 and 100 characters".to_string(),
+            ));
+        }
+        // Exact-match comparison later relies on these being well-formed absolute URLs.
+        for redirect_uri in redirect_uris {
+            match Url::parse(redirect_uri) {
+                Ok(url) if url.fragment().is_none() => {}
+                _ => {
+                    return Err(AppError::BadRequest(format!(
+                        "Invalid redirect URI: {}",
+                        redirect_uri
+                    )))
+                }
+            }
+        }
+
+        let client_secret = confidential.then(|| random_token(48));
+        let client = self
+            .oauth2_repository
+            .create_client(
+                &random_token(24),
+                client_secret.as_deref().map(sha256_hex).as_deref(),
+                name,
+                redirect_uris,
+                scopes,
+            )
+            .await?;
+
+        Ok(NewOAuth2Client {
+            client,
+            client_secret,
+        })
+    }
+
+    async fn list_clients(&self) -> Result<Vec<OAuth2Client>, AppError> {
+        self.oauth2_repository.get_clients().await
+    }
+
+    async fn delete_client(&self, client_id: &str) -> Result<(), AppError> {
+        if !self.oauth2_repository.delete_client(client_id).await? {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn validate_authorization(
+        &self,
+        query: &AuthorizeQuery,
+    ) -> Result<AuthorizationRequest, AuthorizationError> {
+        let client = match self.oauth2_repository.get_client(&query.client_id).await {
+            Ok(client) => client,
+            Err(AppError::NotFound) => {
+                return Err(AuthorizationError::Invalid(AppError::BadRequest(
+                    "Unknown client".to_string(),
+                )))
+            }
+            Err(e) => return Err(AuthorizationError::Invalid(e)),
+        };
+
+        let redirect_uri = match &query.redirect_uri {
+            Some(redirect_uri) if client.redirect_uris.contains(redirect_uri) => {
+                redirect_uri.clone()
+            }
+            None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+            _ => {
+                return Err(AuthorizationError::Invalid(AppError::BadRequest(
+                    "The redirect URI is not registered for this client".to_string(),
+                )))
+            }
+        };
+
+        // From here on the client is told about problems through the redirect URI.
+        let state = query.state.as_deref();
+        let fail = |error: &str, description: &str| {
+            AuthorizationError::Redirect(redirect_url(
+                &redirect_uri,
+                state,
+                &[("error", error), ("error_description", description)],
+            ))
+        };
+
+        if query.response_type != "code" {
+            return Err(fail(
+                "unsupported_response_type",
+                "Only the code response type is supported",
+            ));
+        }
+        let scopes = allowed_scopes(&client, query.scope.as_deref())
+            .ok_or_else(|| fail("invalid_scope", "Scope not allowed for this client"))?;
+
+        match (
+            &query.code_challenge,
+            query.code_challenge_method.as_deref(),
+        ) {
+            (Some(challenge), Some("S256")) if (43..=128).contains(&challenge.len()) => {}
+            (Some(_), _) => {
+                return Err(fail(
+                    "invalid_request",
+                    "code_challenge must be an S256 challenge",
+                ))
+            }
+            (None, _) if !client.is_confidential() => {
+                return Err(fail("invalid_request", "Public clients must use PKCE"))
+            }
+            (None, _) => {}
+        }
+
+        Ok(AuthorizationRequest {
+            client,
+            redirect_uri,
+            scopes,
+            state: query.state.clone(),
+            code_challenge: query.code_challenge.clone(),
+        })
+    }
+
+    async fn approve(
+        &self,
+        user_id: i32,
+        request: &AuthorizationRequest,
+    ) -> Result<String, AppError> {
+        let code = random_token(48);
+        self.oauth2_repository
+            .create_code(
+                &sha256_hex(&code),
+                &request.client.client_id,
+                user_id,
+                &request.redirect_uri,
+                &request.scopes,
+                request.code_challenge.as_deref(),
+                &random_token(32),
+                OffsetDateTime::now_utc() + AUTHORIZATION_CODE_TTL,
+            )
+            .await?;
+
+        Ok(redirect_url(
+            &request.redirect_uri,
+            request.state.as_deref(),
+            &[("code", &code)],
+        ))
+    }
+
+    fn deny(&self, request: &AuthorizationRequest) -> String {
+        redirect_url(
+            &request.redirect_uri,
+            request.state.as_deref(),
+            &[
+                ("error", "access_denied"),
+                ("error_description", "The user denied the request"),
+            ],
+        )
+    }
+
+    async fn exchange(
+        &self,
+        credentials: Option<ClientCredentials>,
+        req: TokenRequest,
+    ) -> Result<TokenResponse, AppError> {
+        let client = self.authenticate_client(credentials).await?;
+
+        match req.grant_type.as_str() {
+            "authorization_code" => self.exchange_code(client, req).await,
+            "refresh_token" => self.refresh(client, req).await,
+            "client_credentials" => self.client_credentials(client, req).await,
+            DEVICE_CODE_GRANT_TYPE => self.exchange_device_code(client, req).await,
+            _ => Err(oauth2_error(
+                "unsupported_grant_type",
+                "Unsupported grant_type",
+            )),
+        }
+    }
+
+    async fn introspect(
+        &self,
+        credentials: Option<ClientCredentials>,
+        token: &str,
+    ) -> Result<IntrospectionResponse, AppError> {
+        // Resource servers introspecting tokens must be able to prove who they are.
+        let client = self.authenticate_client(credentials).await?;
+        if !client.is_confidential() {
+            return Err(oauth2_error(
+                "invalid_client",
+                "Only confidential clients may introspect tokens",
+            ));
+        }
+
+        let stored = match self
+            .oauth2_repository
+            .get_token_by_hash(&sha256_hex(token))
+            .await
+        {
+            Ok(stored) => stored,
+            Err(AppError::NotFound) => return Ok(IntrospectionResponse::default()),
+            Err(e) => return Err(e),
+        };
+        if stored.revoked_at.is_some() || stored.expires_at <= OffsetDateTime::now_utc() {
+            return Ok(IntrospectionResponse::default());
+        }
+
+        let (sub, username) = match stored.user_id {
+            Some(user_id) => {
+                let user = self.user_repository.get_user_by_id(user_id).await?;
+                (user.id.to_string(), Some(user.username))
+            }
+            None => (stored.client_id.clone(), None),
+        };
+
+        Ok(IntrospectionResponse {
+            active: true,
+            scope: Some(stored.scopes.join(" ")),
+            client_id: Some(stored.client_id),
+            username,
+            token_type: Some(if stored.token_type == ACCESS_TOKEN_TYPE {
+                "Bearer".to_string()
+            } else {
+                stored.token_type
+            }),
+            exp: Some(stored.expires_at.unix_timestamp()),
+            iat: Some(stored.created_at.unix_timestamp()),
+            sub: Some(sub),
+        })
+    }
+
+    async fn revoke(
+        &self,
+        credentials: Option<ClientCredentials>,
+        token: &str,
+    ) -> Result<(), AppError> {
+        let client = self.authenticate_client(credentials).await?;
+
+        // Unknown tokens and tokens of other clients are ignored, as RFC 7009
+        // treats an invalid token as already revoked.
+        let stored = match self
+            .oauth2_repository
+            .get_token_by_hash(&sha256_hex(token))
+            .await
+        {
+            Ok(stored) if stored.client_id == client.client_id => stored,
+            Ok(_) | Err(AppError::NotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        if stored.token_type == REFRESH_TOKEN_TYPE {
+            // Access tokens obtained with the refresh token go with it.
+            self.oauth2_repository.revoke_grant(&stored.grant_id).await
+        } else {
+            self.oauth2_repository.revoke_token(stored.id).await?;
+            Ok(())
+        }
+    }
+
+    async fn authenticate(&self, access_token: &str) -> Result<(User, OAuth2Token), AppError> {
+        let stored = match self
+            .oauth2_repository
+            .get_token_by_hash(&sha256_hex(access_token))
+            .await
+        {
+            Ok(stored) => stored,
+            Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+        if stored.token_type != ACCESS_TOKEN_TYPE
+            || stored.revoked_at.is_some()
+            || stored.expires_at <= OffsetDateTime::now_utc()
+        {
+            return Err(AppError::Unauthorized);
+        }
+        // Tokens from the client_credentials grant act for the client, not for a user.
+        let user_id = stored.user_id.ok_or(AppError::Unauthorized)?;
+        let user = self.user_repository.get_user_by_id(user_id).await?;
+
+        Ok((user, stored))
+    }
+
+    async fn start_device_authorization(
+        &self,
+        credentials: Option<ClientCredentials>,
+        scope: Option<&str>,
+    ) -> Result<DeviceAuthorizationResponse, AppError> {
+        // CLIs are usually public clients, so a client_id without a secret is enough.
+        let client = self.authenticate_client(credentials).await?;
+        let scopes = allowed_scopes(&client, scope)
+            .ok_or_else(|| oauth2_error("invalid_scope", "Scope not allowed for this client"))?;
+
+        let device_code = random_token(48);
+        let mut user_code = None;
+        for _ in 0..USER_CODE_ATTEMPTS {
+            let candidate = random_user_code();
+            if self
+                .oauth2_repository
+                .create_device_code(
+                    &sha256_hex(&device_code),
+                    &candidate,
+                    &client.client_id,
+                    &scopes,
+                    DEVICE_POLL_INTERVAL,
+                    OffsetDateTime::now_utc() + DEVICE_CODE_TTL,
+                )
+                .await?
+            {
+                user_code = Some(candidate);
+                break;
+            }
+        }
+        let user_code = user_code.ok_or(AppError::InternalServerError)?;
+
+        let user_code = format_user_code(&user_code);
+        Ok(DeviceAuthorizationResponse {
+            device_code,
+            verification_uri_complete: format!("{}?user_code={}", self.verification_uri, user_code),
+            verification_uri: self.verification_uri.clone(),
+            user_code,
+            expires_in: DEVICE_CODE_TTL.whole_seconds(),
+            interval: DEVICE_POLL_INTERVAL,
+        })
+    }
+
+    async fn pending_device_authorization(
+        &self,
+        user_id: i32,
+        client_ip: IpAddr,
+        user_code: &str,
+    ) -> Result<PendingDeviceAuthorization, AppError> {
+        self.limit_user_code_entries(user_id, client_ip).await?;
+        let device_code = self
+            .oauth2_repository
+            .get_pending_device_code(&normalize_user_code(user_code))
+            .await?;
+        let client = self
+            .oauth2_repository
+            .get_client(&device_code.client_id)
+            .await?;
+
+        Ok(PendingDeviceAuthorization {
+            client,
+            user_code: format_user_code(&device_code.user_code),
+            scopes: device_code.scopes,
+        })
+    }
+
+    async fn decide_device_authorization(
+        &self,
+        user_id: i32,
+        client_ip: IpAddr,
+        user_code: &str,
+        approved: bool,
+    ) -> Result<(), AppError> {
+        self.limit_user_code_entries(user_id, client_ip).await?;
+        let device_code = self
+            .oauth2_repository
+            .get_pending_device_code(&normalize_user_code(user_code))
+            .await?;
+        let status = if approved {
+            DEVICE_CODE_APPROVED
+        } else {
+            DEVICE_CODE_DENIED
+        };
+
+        if !self
+            .oauth2_repository
+            .decide_device_code(device_code.id, user_id, status)
+            .await?
+        {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+}
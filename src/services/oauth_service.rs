@@ -1,66 +1,314 @@
+use crate::config::{OAuthProviderConfig, UserInfoMapping};
 use crate::error::AppError;
+use crate::models::ExternalIdentity;
+use crate::services::oidc::OidcProvider;
+use crate::services::RateLimitService;
+use crate::utils::crypto::random_token;
+use crate::utils::network::client_network;
 use async_trait::async_trait;
+use oauth2::basic::{
+    BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
+    BasicTokenType,
+};
 use oauth2::TokenResponse;
 use oauth2::{
-    basic::BasicClient, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl,
-    Scope, TokenUrl,
+    AuthUrl, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken, ExtraTokenFields,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, StandardRevocableToken,
+    StandardTokenResponse, TokenUrl,
 };
 use reqwest::Client as HttpClient;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a user has to complete the provider's login page.
+const PENDING_STATE_TTL: Duration = Duration::from_secs(10 * 60);
+/// Authorizations held in memory at once; past this the oldest are dropped.
+const MAX_PENDING_AUTHORIZATIONS: usize = 10_000;
+/// Logins a single network may start per window, so it cannot churn through the cap.
+const AUTHORIZATIONS_PER_WINDOW: i32 = 30;
+const AUTHORIZATION_WINDOW: time::Duration = time::Duration::minutes(10);
+
+/// OpenID Connect providers return an `id_token` next to the access token.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct IdTokenFields {
+    id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+type OAuthClient = Client<
+    BasicErrorResponse,
+    StandardTokenResponse<IdTokenFields, BasicTokenType>,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
 
 #[async_trait]
 pub trait OAuthService: Send + Sync {
-    fn get_authorize_url(&self) -> (String, CsrfToken);
-    async fn exchange_code(&self, code: String) -> Result<String, AppError>;
+    fn provider_names(&self) -> Vec<String>;
+    async fn get_authorize_url(
+        &self,
+        provider: &str,
+        client_ip: IpAddr,
+    ) -> Result<(String, CsrfToken), AppError>;
+    async fn exchange_code(
+        &self,
+        provider: &str,
+        code: String,
+        state: String,
+    ) -> Result<ExternalIdentity, AppError>;
+}
+
+struct OAuthProvider {
+    config: OAuthProviderConfig,
+    oidc: Option<OidcProvider>,
+}
+
+impl OAuthProvider {
+    /// Builds the client, taking any endpoint that is not configured from discovery.
+    async fn oauth_client(&self) -> Result<OAuthClient, AppError> {
+        let (auth_url, token_url) =
+            match (&self.config.auth_url, &self.config.token_url, &self.oidc) {
+                (Some(auth_url), Some(token_url), _) => (auth_url.clone(), token_url.clone()),
+                (auth_url, token_url, Some(oidc)) => {
+                    let discovery = oidc.discovery().await?;
+                    (
+                        auth_url.clone().unwrap_or(discovery.authorization_endpoint),
+                        token_url.clone().unwrap_or(discovery.token_endpoint),
+                    )
+                }
+                _ => return Err(AppError::InternalServerError),
+            };
+
+        Ok(OAuthClient::new(
+            ClientId::new(self.config.client_id.clone()),
+            Some(ClientSecret::new(self.config.client_secret.clone())),
+            AuthUrl::new(auth_url).map_err(|_| AppError::InternalServerError)?,
+            Some(TokenUrl::new(token_url).map_err(|_| AppError::InternalServerError)?),
+        )
+        .set_redirect_uri(
+            RedirectUrl::new(self.config.redirect_url.clone())
+                .map_err(|_| AppError::InternalServerError)?,
+        ))
+    }
+
+    async fn userinfo_url(&self) -> Result<String, AppError> {
+        if let Some(userinfo_url) = &self.config.userinfo_url {
+            return Ok(userinfo_url.clone());
+        }
+        match &self.oidc {
+            Some(oidc) => oidc
+                .discovery()
+                .await?
+                .userinfo_endpoint
+                .ok_or(AppError::InternalServerError),
+            None => Err(AppError::InternalServerError),
+        }
+    }
+}
+
+struct PendingAuthorization {
+    provider: String,
+    pkce_verifier: PkceCodeVerifier,
+    nonce: Option<String>,
+    created_at: Instant,
 }
 
 pub struct OAuthServiceImpl {
-    oauth_client: BasicClient,
+    providers: HashMap<String, OAuthProvider>,
     http_client: HttpClient,
+    rate_limit_service: Arc<dyn RateLimitService>,
+    pending: Mutex<HashMap<String, PendingAuthorization>>,
 }
 
 impl OAuthServiceImpl {
+    /// Builds the provider registry. OpenID Connect providers are discovered on first
+    /// use, so one that is down does not stop the others from working.
     pub fn new(
-        client_id: String,
-        client_secret: String,
-        auth_url: String,
-        token_url: String,
-        redirect_url: String,
-    ) -> Self {
-        let oauth_client = BasicClient::new(
-            ClientId::new(client_id),
-            Some(ClientSecret::new(client_secret)),
-            AuthUrl::new(auth_url).unwrap(),
-            Some(TokenUrl::new(token_url).unwrap()),
-        )
-        .set_redirect_uri(RedirectUrl::new(redirect_url).unwrap());
+        configs: Vec<OAuthProviderConfig>,
+        rate_limit_service: Arc<dyn RateLimitService>,
+    ) -> Result<Self, AppError> {
+        let http_client = HttpClient::new();
+        let mut providers = HashMap::new();
 
-        Self {
-            oauth_client,
-            http_client: HttpClient::new(),
+        for config in configs {
+            // Without an issuer to discover them from, the endpoints must be configured.
+            if config.issuer.is_none() && (config.auth_url.is_none() || config.token_url.is_none())
+            {
+                return Err(AppError::InternalServerError);
+            }
+            let oidc = config.issuer.as_deref().map(|issuer| {
+                OidcProvider::new(http_client.clone(), issuer, config.client_id.clone())
+            });
+
+            providers.insert(config.name.clone(), OAuthProvider { config, oidc });
         }
+
+        Ok(Self {
+            providers,
+            http_client,
+            rate_limit_service,
+            pending: Mutex::new(HashMap::new()),
+        })
     }
+
+    fn provider(&self, name: &str) -> Result<&OAuthProvider, AppError> {
+        self.providers.get(name).ok_or(AppError::NotFound)
+    }
+
+    /// Removes the pending authorization for `state`, failing if it is unknown,
+    /// expired or was started for a different provider.
+    fn take_pending(&self, provider: &str, state: &str) -> Result<PendingAuthorization, AppError> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.created_at.elapsed() < PENDING_STATE_TTL);
+
+        pending
+            .remove(state)
+            .filter(|p| p.provider == provider)
+            .ok_or_else(|| AppError::BadRequest("Invalid or expired OAuth state".to_string()))
+    }
+
+    async fn fetch_userinfo(
+        &self,
+        provider: &OAuthProvider,
+        access_token: &str,
+    ) -> Result<Value, AppError> {
+        let userinfo_url = provider.userinfo_url().await?;
+
+        self.http_client
+            .get(&userinfo_url)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|_| AppError::InternalServerError)?
+            .json()
+            .await
+            .map_err(|_| AppError::InternalServerError)
+    }
+}
+
+/// Maps userinfo or ID token claims to an identity using the provider's field names.
+fn identity_from_claims(
+    provider_name: &str,
+    mapping: &UserInfoMapping,
+    claims: &Value,
+) -> Result<ExternalIdentity, AppError> {
+    let string_field = |field: &str| match claims.get(field) {
+        Some(Value::String(value)) => Some(value.clone()),
+        Some(Value::Number(value)) => Some(value.to_string()),
+        _ => None,
+    };
+
+    Ok(ExternalIdentity {
+        provider: provider_name.to_string(),
+        subject: string_field(&mapping.subject).ok_or(AppError::InternalServerError)?,
+        email: string_field(&mapping.email),
+        email_verified: claims
+            .get(&mapping.email_verified)
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        username: string_field(&mapping.username),
+    })
 }
 
 #[async_trait]
 impl OAuthService for OAuthServiceImpl {
-    fn get_authorize_url(&self) -> (String, CsrfToken) {
-        let (auth_url, csrf_token) = self
-            .oauth_client
+    fn provider_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.providers.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    async fn get_authorize_url(
+        &self,
+        provider_name: &str,
+        client_ip: IpAddr,
+    ) -> Result<(String, CsrfToken), AppError> {
+        let provider = self.provider(provider_name)?;
+        self.rate_limit_service
+            .hit(
+                &format!("oauth_login:{}", client_network(client_ip)),
+                AUTHORIZATIONS_PER_WINDOW,
+                AUTHORIZATION_WINDOW,
+            )
+            .await?;
+        let oauth_client = provider.oauth_client().await?;
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let nonce = provider.oidc.as_ref().map(|_| random_token(32));
+
+        let mut request = oauth_client
             .authorize_url(CsrfToken::new_random)
-            .add_scope(Scope::new("read".to_string()))
-            .url();
-        (auth_url.to_string(), csrf_token)
+            .add_scopes(provider.config.scopes.iter().cloned().map(Scope::new))
+            .set_pkce_challenge(pkce_challenge);
+        if let Some(nonce) = &nonce {
+            request = request.add_extra_param("nonce", nonce.clone());
+        }
+        let (auth_url, csrf_token) = request.url();
+
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, p| p.created_at.elapsed() < PENDING_STATE_TTL);
+        if pending.len() >= MAX_PENDING_AUTHORIZATIONS {
+            let oldest = pending
+                .iter()
+                .min_by_key(|(_, p)| p.created_at)
+                .map(|(state, _)| state.clone());
+            if let Some(oldest) = oldest {
+                pending.remove(&oldest);
+            }
+        }
+        pending.insert(
+            csrf_token.secret().clone(),
+            PendingAuthorization {
+                provider: provider_name.to_string(),
+                pkce_verifier,
+                nonce,
+                created_at: Instant::now(),
+            },
+        );
+
+        Ok((auth_url.to_string(), csrf_token))
     }
 
-    async fn exchange_code(&self, code: String) -> Result<String, AppError> {
-        let token = self
-            .oauth_client
+    async fn exchange_code(
+        &self,
+        provider_name: &str,
+        code: String,
+        state: String,
+    ) -> Result<ExternalIdentity, AppError> {
+        let provider = self.provider(provider_name)?;
+        let pending = self.take_pending(provider_name, &state)?;
+
+        let token = provider
+            .oauth_client()
+            .await?
             .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(pending.pkce_verifier)
             .request_async(oauth2::reqwest::async_http_client)
             .await
             .map_err(|_| AppError::InternalServerError)?;
 
-        Ok(token.access_token().secret().to_string())
+        let claims = match (&provider.oidc, &pending.nonce) {
+            (Some(oidc), Some(nonce)) => {
+                let id_token = token
+                    .extra_fields()
+                    .id_token
+                    .as_deref()
+                    .ok_or(AppError::Unauthorized)?;
+                oidc.validate_id_token(id_token, nonce).await?
+            }
+            _ => {
+                self.fetch_userinfo(provider, token.access_token().secret())
+                    .await?
+            }
+        };
+
+        identity_from_claims(provider_name, &provider.config.userinfo_mapping, &claims)
     }
 }
@@ -0,0 +1,396 @@
+use crate::error::AppError;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Keys are re-fetched after this long, or sooner when an unknown `kid` shows up.
+const JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+/// Stops tokens with made-up `kid`s from making us hammer the provider.
+const JWKS_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The parts of `.well-known/openid-configuration` we rely on.
+#[derive(Clone, Deserialize)]
+pub struct DiscoveryDocument {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+    #[serde(default = "default_signing_algs")]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+}
+
+/// RS256 is the algorithm every provider must support.
+fn default_signing_algs() -> Vec<String> {
+    vec!["RS256".to_string()]
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+pub struct OidcProvider {
+    issuer: String,
+    client_id: String,
+    http_client: HttpClient,
+    discovery: RwLock<Option<DiscoveryDocument>>,
+    jwks: RwLock<Option<CachedJwks>>,
+}
+
+impl OidcProvider {
+    pub fn new(http_client: HttpClient, issuer: &str, client_id: String) -> Self {
+        Self {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id,
+            http_client,
+            discovery: RwLock::new(None),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    /// The provider's discovery document, fetched the first time it is needed so an
+    /// unreachable provider does not keep the app from starting.
+    pub async fn discovery(&self) -> Result<DiscoveryDocument, AppError> {
+        if let Some(discovery) = self.discovery.read().await.as_ref() {
+            return Ok(discovery.clone());
+        }
+
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+        let discovery: DiscoveryDocument = self
+            .http_client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|_| AppError::InternalServerError)?
+            .json()
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+        // The document must describe the issuer we were configured with.
+        if discovery.issuer.trim_end_matches('/') != self.issuer {
+            return Err(AppError::InternalServerError);
+        }
+
+        *self.discovery.write().await = Some(discovery.clone());
+        Ok(discovery)
+    }
+
+    /// Validates the signature, issuer, audience, expiry and nonce of an ID token
+    /// and returns its claims.
+    pub async fn validate_id_token(&self, id_token: &str, nonce: &str) -> Result<Value, AppError> {
+        let discovery = self.discovery().await?;
+        let header = decode_header(id_token).map_err(|_| AppError::Unauthorized)?;
+        let kid = header.kid.ok_or(AppError::Unauthorized)?;
+        let jwk = self.jwk(&discovery, &kid).await?;
+
+        // The token's own header must not get to pick how it is verified.
+        if !allowed_algorithms(&jwk, &discovery).contains(&header.alg) {
+            return Err(AppError::Unauthorized);
+        }
+        let key = DecodingKey::from_jwk(&jwk).map_err(|_| AppError::Unauthorized)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&discovery.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<Value>(id_token, &key, &validation)
+            .map_err(|_| AppError::Unauthorized)?
+            .claims;
+
+        if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+            return Err(AppError::Unauthorized);
+        }
+
+        Ok(claims)
+    }
+
+    async fn jwk(&self, discovery: &DiscoveryDocument, kid: &str) -> Result<Jwk, AppError> {
+        {
+            let jwks = self.jwks.read().await;
+            if let Some(cached) = jwks.as_ref() {
+                let age = cached.fetched_at.elapsed();
+                match cached.keys.find(kid) {
+                    Some(jwk) if age < JWKS_TTL => return Ok(jwk.clone()),
+                    None if age < JWKS_MIN_REFRESH_INTERVAL => return Err(AppError::Unauthorized),
+                    _ => {}
+                }
+            }
+        }
+
+        // Either the cache is stale or the provider rotated its keys.
+        let keys: JwkSet = self
+            .http_client
+            .get(&discovery.jwks_uri)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|_| AppError::InternalServerError)?
+            .json()
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+        let jwk = keys.find(kid).cloned().ok_or(AppError::Unauthorized);
+
+        *self.jwks.write().await = Some(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+        });
+
+        jwk
+    }
+}
+
+/// The algorithm the key is published for, or else those the provider advertises.
+/// HMAC is never accepted, since the shared secret is not a key the provider signs with.
+fn allowed_algorithms(jwk: &Jwk, discovery: &DiscoveryDocument) -> Vec<Algorithm> {
+    let names = match &jwk.common.key_algorithm {
+        Some(alg) => vec![format!("{alg:?}")],
+        None => discovery.id_token_signing_alg_values_supported.clone(),
+    };
+
+    names
+        .iter()
+        .filter_map(|name| name.parse().ok())
+        .filter(|alg| !matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+    use jsonwebtoken::jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, KeyAlgorithm, OctetKeyPairParameters,
+        OctetKeyPairType,
+    };
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const CLIENT_ID: &str = "my-app";
+    const NONCE: &str = "expected-nonce";
+    const KID: &str = "key-1";
+
+    struct Issuer {
+        url: String,
+        signing_key: EncodingKey,
+    }
+
+    /// Starts a provider publishing discovery and JWKS documents for one Ed25519 key.
+    /// `key_algorithm` is the key's `alg`, and `advertised` the algorithms listed in
+    /// discovery. `document_issuer` overrides the `issuer` the discovery document claims.
+    async fn issuer(
+        key_algorithm: Option<KeyAlgorithm>,
+        advertised: &[&str],
+        document_issuer: Option<&str>,
+    ) -> Issuer {
+        let private_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let der = private_key.to_pkcs8_der().unwrap();
+        let jwk = Jwk {
+            common: CommonParameters {
+                key_algorithm,
+                key_id: Some(KID.to_string()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(private_key.verifying_key().as_bytes()),
+            }),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let discovery = json!({
+            "issuer": document_issuer.unwrap_or(&url),
+            "authorization_endpoint": format!("{url}/authorize"),
+            "token_endpoint": format!("{url}/token"),
+            "jwks_uri": format!("{url}/jwks"),
+            "id_token_signing_alg_values_supported": advertised,
+        });
+        let jwks = json!({ "keys": [jwk] });
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(move || async move { Json(discovery) }),
+            )
+            .route("/jwks", get(move || async move { Json(jwks) }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Issuer {
+            url,
+            signing_key: EncodingKey::from_ed_der(der.as_bytes()),
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    impl Issuer {
+        fn provider(&self) -> OidcProvider {
+            OidcProvider::new(HttpClient::new(), &self.url, CLIENT_ID.to_string())
+        }
+
+        fn claims(&self) -> Value {
+            json!({
+                "iss": self.url,
+                "aud": CLIENT_ID,
+                "sub": "user-1",
+                "exp": now() + 300,
+                "nonce": NONCE,
+            })
+        }
+
+        fn sign(&self, claims: &Value, kid: &str) -> String {
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some(kid.to_string());
+            encode(&header, claims, &self.signing_key).unwrap()
+        }
+    }
+
+    async fn eddsa_issuer() -> Issuer {
+        issuer(Some(KeyAlgorithm::EdDSA), &["EdDSA"], None).await
+    }
+
+    async fn validate(issuer: &Issuer, claims: &Value) -> Result<Value, AppError> {
+        issuer
+            .provider()
+            .validate_id_token(&issuer.sign(claims, KID), NONCE)
+            .await
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_token() {
+        let issuer = eddsa_issuer().await;
+
+        let claims = validate(&issuer, &issuer.claims()).await.unwrap();
+
+        assert_eq!(claims["sub"], "user-1");
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_issuer() {
+        let issuer = eddsa_issuer().await;
+        let mut claims = issuer.claims();
+        claims["iss"] = json!("https://evil.example.com");
+
+        assert!(matches!(
+            validate(&issuer, &claims).await,
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_audience() {
+        let issuer = eddsa_issuer().await;
+        let mut claims = issuer.claims();
+        claims["aud"] = json!("another-app");
+
+        assert!(matches!(
+            validate(&issuer, &claims).await,
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_nonce() {
+        let issuer = eddsa_issuer().await;
+        let mut claims = issuer.claims();
+        claims["nonce"] = json!("replayed-nonce");
+
+        assert!(matches!(
+            validate(&issuer, &claims).await,
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_token() {
+        let issuer = eddsa_issuer().await;
+        let mut claims = issuer.claims();
+        claims["exp"] = json!(now() - 3600);
+
+        assert!(matches!(
+            validate(&issuer, &claims).await,
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_kid() {
+        let issuer = eddsa_issuer().await;
+        let token = issuer.sign(&issuer.claims(), "key-2");
+
+        let result = issuer.provider().validate_id_token(&token, NONCE).await;
+
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn rejects_algorithm_other_than_the_keys() {
+        let issuer = issuer(Some(KeyAlgorithm::RS256), &["RS256", "EdDSA"], None).await;
+
+        assert!(matches!(
+            validate(&issuer, &issuer.claims()).await,
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_algorithm_the_provider_does_not_advertise() {
+        let issuer = issuer(None, &["RS256"], None).await;
+
+        assert!(matches!(
+            validate(&issuer, &issuer.claims()).await,
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn accepts_advertised_algorithm_for_keys_without_one() {
+        let issuer = issuer(None, &["RS256", "EdDSA"], None).await;
+
+        assert!(validate(&issuer, &issuer.claims()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_discovery_for_another_issuer() {
+        let issuer = issuer(
+            Some(KeyAlgorithm::EdDSA),
+            &["EdDSA"],
+            Some("https://evil.example.com"),
+        )
+        .await;
+
+        assert!(matches!(
+            issuer.provider().discovery().await,
+            Err(AppError::InternalServerError)
+        ));
+    }
+
+    #[tokio::test]
+    async fn discovers_lazily() {
+        // Nothing listens here, yet the provider can still be set up.
+        let provider = OidcProvider::new(
+            HttpClient::new(),
+            "http://127.0.0.1:1",
+            CLIENT_ID.to_string(),
+        );
+
+        assert!(provider.discovery().await.is_err());
+    }
+}
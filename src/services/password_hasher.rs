@@ -0,0 +1,97 @@
+use crate::error::AppError;
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHasher as _, PasswordVerifier as _, Version};
+use async_trait::async_trait;
+
+const ARGON2ID_PREFIX: &str = "$argon2id$";
+const BCRYPT_PREFIXES: [&str; 3] = ["$2a$", "$2b$", "$2y$"];
+
+#[async_trait]
+pub trait PasswordHasher: Send + Sync {
+    async fn hash(&self, password: &str) -> Result<String, AppError>;
+    async fn verify(&self, password: &str, password_hash: &str) -> Result<bool, AppError>;
+    /// Whether `password_hash` was produced with an algorithm or cost other than the current one.
+    fn needs_rehash(&self, password_hash: &str) -> bool;
+}
+
+/// Hashes new passwords with argon2id and still verifies legacy bcrypt hashes.
+pub struct PasswordHasherImpl {
+    params: Params,
+}
+
+impl PasswordHasherImpl {
+    pub fn new() -> Self {
+        Self {
+            params: Params::default(),
+        }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+}
+
+impl Default for PasswordHasherImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl PasswordHasher for PasswordHasherImpl {
+    async fn hash(&self, password: &str) -> Result<String, AppError> {
+        let argon2 = self.argon2();
+        let password = password.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon2
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|_| AppError::InternalServerError)
+        })
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+    }
+
+    async fn verify(&self, password: &str, password_hash: &str) -> Result<bool, AppError> {
+        let argon2 = self.argon2();
+        let password = password.to_string();
+        let password_hash = password_hash.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            if password_hash.starts_with(ARGON2ID_PREFIX) {
+                let parsed =
+                    PasswordHash::new(&password_hash).map_err(|_| AppError::InternalServerError)?;
+                // Verification uses the parameters embedded in the hash, not our own.
+                Ok(argon2.verify_password(password.as_bytes(), &parsed).is_ok())
+            } else if BCRYPT_PREFIXES.iter().any(|p| password_hash.starts_with(p)) {
+                bcrypt::verify(&password, &password_hash).map_err(|_| AppError::InternalServerError)
+            } else {
+                // A hash we cannot read should fail the login, not the request.
+                tracing::warn!("password hash has an unrecognised format");
+                Ok(false)
+            }
+        })
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+    }
+
+    fn needs_rehash(&self, password_hash: &str) -> bool {
+        if !password_hash.starts_with(ARGON2ID_PREFIX) {
+            return true;
+        }
+
+        let Ok(parsed) = PasswordHash::new(password_hash) else {
+            return true;
+        };
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
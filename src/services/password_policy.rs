@@ -0,0 +1,205 @@
+use crate::config::PasswordPolicyConfig;
+use crate::error::AppError;
+use crate::models::FieldError;
+use async_trait::async_trait;
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Length of the hash prefix the breached corpus is bucketed by, as in k-anonymity range queries.
+const PREFIX_LEN: usize = 5;
+
+#[async_trait]
+pub trait PasswordPolicy: Send + Sync {
+    /// Returns every rule `password` breaks as a `AppError::Validation`.
+    async fn validate(
+        &self,
+        username: &str,
+        email: Option<&str>,
+        password: &str,
+    ) -> Result<(), AppError>;
+}
+
+pub struct PasswordPolicyImpl {
+    config: PasswordPolicyConfig,
+    /// Range files are read on demand, since the full corpus is far too large to keep in memory.
+    breached_dir: Option<PathBuf>,
+}
+
+impl PasswordPolicyImpl {
+    pub fn new(config: PasswordPolicyConfig) -> io::Result<Self> {
+        let breached_dir = config.breached_passwords_dir.as_ref().map(PathBuf::from);
+        // Fail at startup rather than quietly skipping the check on every registration.
+        if let Some(dir) = &breached_dir {
+            if !dir.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("breached password directory {} not found", dir.display()),
+                ));
+            }
+        }
+
+        Ok(Self {
+            config,
+            breached_dir,
+        })
+    }
+
+    async fn is_breached(&self, password: &str) -> Result<bool, AppError> {
+        let Some(dir) = &self.breached_dir else {
+            return Ok(false);
+        };
+        let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+
+        let path = dir.join(format!("{prefix}.txt"));
+        // The range files are read with blocking IO, so keep it off the async workers.
+        let lookup = {
+            let (path, suffix) = (path.clone(), suffix.to_string());
+            tokio::task::spawn_blocking(move || range_contains(&path, &suffix))
+        };
+        match lookup.await.map_err(|_| AppError::InternalServerError)? {
+            Ok(found) => Ok(found),
+            Err(e) => {
+                tracing::warn!(
+                    "could not read breached password range {}: {}",
+                    path.display(),
+                    e
+                );
+                if self.config.breached_passwords_fail_open {
+                    Ok(false)
+                } else {
+                    Err(AppError::InternalServerError)
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PasswordPolicy for PasswordPolicyImpl {
+    async fn validate(
+        &self,
+        username: &str,
+        email: Option<&str>,
+        password: &str,
+    ) -> Result<(), AppError> {
+        let config = &self.config;
+        let mut errors = Vec::new();
+        let mut error = |message: String| errors.push(FieldError::new("password", message));
+
+        let length = password.chars().count();
+        if length < config.min_length {
+            error(format!(
+                "Must be at least {} characters long",
+                config.min_length
+            ));
+        }
+        if length > config.max_length {
+            error(format!(
+                "Must be at most {} characters long",
+                config.max_length
+            ));
+        }
+        if config.require_lowercase && !password.chars().any(char::is_lowercase) {
+            error("Must contain a lowercase letter".to_string());
+        }
+        if config.require_uppercase && !password.chars().any(char::is_uppercase) {
+            error("Must contain an uppercase letter".to_string());
+        }
+        if config.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            error("Must contain a digit".to_string());
+        }
+        if config.require_symbol && password.chars().all(char::is_alphanumeric) {
+            error("Must contain a symbol".to_string());
+        }
+
+        let lowered = password.to_lowercase();
+        if lowered == username.to_lowercase()
+            || email.is_some_and(|email| lowered == email.to_lowercase())
+        {
+            error("Must not match your username or email".to_string());
+        }
+
+        if self.is_breached(password).await? {
+            error("Has appeared in a data breach, please choose another".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(errors))
+        }
+    }
+}
+
+/// Whether the range file at `path` lists `suffix` with a non-zero count. Padding
+/// entries added by the range API have a count of zero and are ignored.
+fn range_contains(path: &Path, suffix: &str) -> io::Result<bool> {
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let Some((candidate, count)) = line.trim().split_once(':') else {
+            continue;
+        };
+        if candidate.eq_ignore_ascii_case(suffix) {
+            return Ok(count.trim().parse::<u64>().is_ok_and(|count| count > 0));
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::random_token;
+
+    const BREACHED: &str = "password";
+
+    /// A corpus directory holding only the range file for `BREACHED`.
+    fn corpus() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("breached-{}", random_token(12)));
+        std::fs::create_dir(&dir).unwrap();
+        let hash = hex::encode_upper(Sha1::digest(BREACHED.as_bytes()));
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+        std::fs::write(dir.join(format!("{prefix}.txt")), format!("{suffix}:3\r\n")).unwrap();
+        dir
+    }
+
+    fn policy(dir: &Path, fail_open: bool) -> PasswordPolicyImpl {
+        PasswordPolicyImpl::new(PasswordPolicyConfig {
+            min_length: 1,
+            max_length: 128,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            breached_passwords_dir: Some(dir.display().to_string()),
+            breached_passwords_fail_open: fail_open,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rejects_breached_password() {
+        let result = policy(&corpus(), false)
+            .validate("alice", None, BREACHED)
+            .await;
+
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn unreadable_range_fails_closed_unless_configured() {
+        let dir = corpus();
+
+        let closed = policy(&dir, false)
+            .validate("alice", None, "unlisted")
+            .await;
+        let open = policy(&dir, true).validate("alice", None, "unlisted").await;
+
+        assert!(matches!(closed, Err(AppError::InternalServerError)));
+        assert!(open.is_ok());
+    }
+}
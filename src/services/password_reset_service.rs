@@ -0,0 +1,167 @@
+use crate::error::AppError;
+use crate::repositories::{
+    OAuth2Repository, PasswordResetRepository, RefreshTokenRepository, UserRepository,
+};
+use crate::services::mailer::{Email, Mailer};
+use crate::services::{PasswordHasher, PasswordPolicy, RateLimitService, RevocationService};
+use crate::utils::crypto::{random_token, sha256_hex};
+use crate::utils::network::client_network;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+const RESET_TOKEN_TTL: time::Duration = time::Duration::hours(1);
+/// Reset links one address may be sent per window, so it cannot be flooded with mail.
+const RESETS_PER_ADDRESS: i32 = 3;
+/// Resets one network may ask for per window, whichever addresses it names.
+const RESETS_PER_NETWORK: i32 = 10;
+const RESET_WINDOW: time::Duration = time::Duration::hours(1);
+
+#[async_trait]
+pub trait PasswordResetService: Send + Sync {
+    /// Emails a reset link if `email` belongs to an account, and silently does nothing otherwise.
+    async fn request_reset(&self, email: &str, client_ip: IpAddr) -> Result<(), AppError>;
+    /// Sets a new password and signs the user out everywhere.
+    async fn reset_password(&self, token: &str, password: &str) -> Result<(), AppError>;
+}
+
+pub struct PasswordResetServiceImpl {
+    user_repository: Arc<dyn UserRepository>,
+    password_reset_repository: Arc<dyn PasswordResetRepository>,
+    refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+    oauth2_repository: Arc<dyn OAuth2Repository>,
+    revocation_service: Arc<dyn RevocationService>,
+    password_hasher: Arc<dyn PasswordHasher>,
+    password_policy: Arc<dyn PasswordPolicy>,
+    mailer: Arc<dyn Mailer>,
+    rate_limit_service: Arc<dyn RateLimitService>,
+    app_base_url: String,
+}
+
+impl PasswordResetServiceImpl {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        password_reset_repository: Arc<dyn PasswordResetRepository>,
+        refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+        oauth2_repository: Arc<dyn OAuth2Repository>,
+        revocation_service: Arc<dyn RevocationService>,
+        password_hasher: Arc<dyn PasswordHasher>,
+        password_policy: Arc<dyn PasswordPolicy>,
+        mailer: Arc<dyn Mailer>,
+        rate_limit_service: Arc<dyn RateLimitService>,
+        app_base_url: String,
+    ) -> Self {
+        Self {
+            user_repository,
+            password_reset_repository,
+            refresh_token_repository,
+            oauth2_repository,
+            revocation_service,
+            password_hasher,
+            password_policy,
+            mailer,
+            rate_limit_service,
+            app_base_url,
+        }
+    }
+}
+
+fn invalid_link() -> AppError {
+    AppError::BadRequest("Invalid or expired reset link".to_string())
+}
+
+#[async_trait]
+impl PasswordResetService for PasswordResetServiceImpl {
+    async fn request_reset(&self, email: &str, client_ip: IpAddr) -> Result<(), AppError> {
+        // Counted before the lookup, so unknown addresses use up the limits just the same.
+        self.rate_limit_service
+            .hit(
+                &format!("password_reset:ip:{}", client_network(client_ip)),
+                RESETS_PER_NETWORK,
+                RESET_WINDOW,
+            )
+            .await?;
+        self.rate_limit_service
+            .hit(
+                &format!("password_reset:email:{}", email.trim().to_lowercase()),
+                RESETS_PER_ADDRESS,
+                RESET_WINDOW,
+            )
+            .await?;
+
+        let user = match self.user_repository.get_user_by_email(email).await {
+            Ok(user) => user,
+            Err(AppError::NotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let token = random_token(64);
+        self.password_reset_repository
+            .create_token(
+                user.id,
+                &sha256_hex(&token),
+                OffsetDateTime::now_utc() + RESET_TOKEN_TTL,
+            )
+            .await?;
+
+        let link = format!(
+            "{}/password/reset?token={}",
+            self.app_base_url.trim_end_matches('/'),
+            token
+        );
+        self.mailer
+            .send(Email {
+                to: email.to_string(),
+                subject: "Reset your password".to_string(),
+                body: format!(
+                    "Hi {},\n\nSomeone asked to reset the password for your account. If it was you, open the link below within the next hour. Otherwise you can ignore this email.\n\n{}\n",
+                    user.username, link
+                ),
+            })
+            .await
+    }
+
+    async fn reset_password(&self, token: &str, password: &str) -> Result<(), AppError> {
+        let stored = match self
+            .password_reset_repository
+            .get_token_by_hash(&sha256_hex(token))
+            .await
+        {
+            Ok(stored) => stored,
+            Err(AppError::NotFound) => return Err(invalid_link()),
+            Err(e) => return Err(e),
+        };
+        if stored.used_at.is_some() || stored.expires_at <= OffsetDateTime::now_utc() {
+            return Err(invalid_link());
+        }
+
+        let user = self.user_repository.get_user_by_id(stored.user_id).await?;
+        self.password_policy
+            .validate(&user.username, user.email.as_deref(), password)
+            .await?;
+        let password_hash = self.password_hasher.hash(password).await?;
+
+        // Claiming the token last keeps a rejected password from burning the link.
+        if !self.password_reset_repository.mark_used(stored.id).await? {
+            return Err(invalid_link());
+        }
+
+        self.user_repository
+            .update_password_hash(user.id, &password_hash)
+            .await?;
+        // Receiving the link proves the user controls the address.
+        if let Some(email) = user.email.as_deref() {
+            self.user_repository
+                .mark_email_verified(user.id, email)
+                .await?;
+        }
+
+        self.refresh_token_repository
+            .revoke_all_for_user(user.id)
+            .await?;
+        self.oauth2_repository.revoke_all_for_user(user.id).await?;
+        self.revocation_service.revoke_all_for_user(user.id).await
+    }
+}
@@ -0,0 +1,46 @@
+use crate::error::AppError;
+use crate::repositories::PermissionRepository;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait PermissionService: Send + Sync {
+    async fn get_user_roles(&self, user_id: i32) -> Result<Vec<String>, AppError>;
+    async fn has_permission(&self, user_id: i32, permission: &str) -> Result<bool, AppError>;
+    async fn assign_role(&self, user_id: i32, role: &str) -> Result<(), AppError>;
+    async fn remove_role(&self, user_id: i32, role: &str) -> Result<(), AppError>;
+}
+
+pub struct PermissionServiceImpl {
+    permission_repository: Arc<dyn PermissionRepository>,
+}
+
+impl PermissionServiceImpl {
+    pub fn new(permission_repository: Arc<dyn PermissionRepository>) -> Self {
+        Self {
+            permission_repository,
+        }
+    }
+}
+
+#[async_trait]
+impl PermissionService for PermissionServiceImpl {
+    async fn get_user_roles(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        let roles = self.permission_repository.get_user_roles(user_id).await?;
+        Ok(roles.into_iter().map(|role| role.name).collect())
+    }
+
+    async fn has_permission(&self, user_id: i32, permission: &str) -> Result<bool, AppError> {
+        self.permission_repository
+            .user_has_permission(user_id, permission)
+            .await
+    }
+
+    async fn assign_role(&self, user_id: i32, role: &str) -> Result<(), AppError> {
+        self.permission_repository.assign_role(user_id, role).await
+    }
+
+    async fn remove_role(&self, user_id: i32, role: &str) -> Result<(), AppError> {
+        self.permission_repository.remove_role(user_id, role).await
+    }
+}
@@ -0,0 +1,76 @@
+use crate::error::AppError;
+use crate::repositories::RateLimitRepository;
+use async_trait::async_trait;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+#[async_trait]
+pub trait RateLimitService: Send + Sync {
+    /// Counts a request against `key`, failing with `AppError::TooManyRequests` once
+    /// more than `limit` were made in the current `window`.
+    async fn hit(&self, key: &str, limit: i32, window: Duration) -> Result<(), AppError>;
+}
+
+pub struct RateLimitServiceImpl {
+    rate_limit_repository: Arc<dyn RateLimitRepository>,
+}
+
+impl RateLimitServiceImpl {
+    pub fn new(rate_limit_repository: Arc<dyn RateLimitRepository>) -> Self {
+        Self {
+            rate_limit_repository,
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimitService for RateLimitServiceImpl {
+    async fn hit(&self, key: &str, limit: i32, window: Duration) -> Result<(), AppError> {
+        let rate_limit = self
+            .rate_limit_repository
+            .hit(key, window.whole_seconds())
+            .await?;
+
+        if rate_limit.hits > limit {
+            let window_ends_at = rate_limit.window_started_at + window;
+            return Err(AppError::TooManyRequests {
+                retry_after: (window_ends_at - OffsetDateTime::now_utc())
+                    .whole_seconds()
+                    .max(1) as u64,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Counts hits in memory without ever resetting, so tests can run into limits.
+#[cfg(any(test, feature = "test_utils"))]
+#[derive(Default)]
+pub struct InMemoryRateLimiter {
+    hits: std::sync::Mutex<std::collections::HashMap<String, i32>>,
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+impl InMemoryRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(any(test, feature = "test_utils"))]
+#[async_trait]
+impl RateLimitService for InMemoryRateLimiter {
+    async fn hit(&self, key: &str, limit: i32, window: Duration) -> Result<(), AppError> {
+        let mut hits = self.hits.lock().unwrap();
+        let count = hits.entry(key.to_string()).or_default();
+        *count += 1;
+        if *count > limit {
+            return Err(AppError::TooManyRequests {
+                retry_after: window.whole_seconds() as u64,
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::AppError;
+use crate::repositories::RevocationRepository;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use tokio::sync::RwLock;
+
+/// How long the in-memory view may lag behind revocations made by other instances.
+const CACHE_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[async_trait]
+pub trait RevocationService: Send + Sync {
+    async fn revoke_token(
+        &self,
+        jti: &str,
+        user_id: i32,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError>;
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError>;
+    async fn is_revoked(
+        &self,
+        jti: &str,
+        user_id: i32,
+        issued_at: OffsetDateTime,
+    ) -> Result<bool, AppError>;
+}
+
+struct RevocationCache {
+    tokens: HashMap<String, OffsetDateTime>,
+    users: HashMap<i32, OffsetDateTime>,
+    synced_at: Option<Instant>,
+}
+
+pub struct RevocationServiceImpl {
+    revocation_repository: Arc<dyn RevocationRepository>,
+    cache: RwLock<RevocationCache>,
+}
+
+impl RevocationServiceImpl {
+    pub fn new(revocation_repository: Arc<dyn RevocationRepository>) -> Self {
+        Self {
+            revocation_repository,
+            cache: RwLock::new(RevocationCache {
+                tokens: HashMap::new(),
+                users: HashMap::new(),
+                synced_at: None,
+            }),
+        }
+    }
+
+    async fn sync_if_stale(&self) -> Result<(), AppError> {
+        let is_stale = |cache: &RevocationCache| {
+            cache
+                .synced_at
+                .is_none_or(|synced_at| synced_at.elapsed() >= CACHE_REFRESH_INTERVAL)
+        };
+
+        if !is_stale(&*self.cache.read().await) {
+            return Ok(());
+        }
+
+        let mut cache = self.cache.write().await;
+        // Another request may have refreshed the cache while we waited for the lock.
+        if !is_stale(&cache) {
+            return Ok(());
+        }
+
+        let tokens = self
+            .revocation_repository
+            .get_active_revoked_tokens()
+            .await?;
+        let users = self.revocation_repository.get_user_revocations().await?;
+
+        cache.tokens = tokens
+            .into_iter()
+            .map(|token| (token.jti, token.expires_at))
+            .collect();
+        cache.users = users
+            .into_iter()
+            .map(|revocation| (revocation.user_id, revocation.revoked_before))
+            .collect();
+        cache.synced_at = Some(Instant::now());
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RevocationService for RevocationServiceImpl {
+    async fn revoke_token(
+        &self,
+        jti: &str,
+        user_id: i32,
+        expires_at: OffsetDateTime,
+    ) -> Result<(), AppError> {
+        self.revocation_repository
+            .revoke_token(jti, user_id, expires_at)
+            .await?;
+        self.cache
+            .write()
+            .await
+            .tokens
+            .insert(jti.to_string(), expires_at);
+
+        Ok(())
+    }
+
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError> {
+        // `iat` has whole-second precision, so a token from the same second as the cutoff
+        // cannot be told apart from one issued just before it and is revoked too. A new
+        // login in that second has to be repeated, which beats leaving a stolen token live.
+        let now = OffsetDateTime::now_utc();
+        let now = now - time::Duration::nanoseconds(now.nanosecond().into());
+
+        self.revocation_repository
+            .revoke_user_tokens(user_id, now)
+            .await?;
+        self.cache.write().await.users.insert(user_id, now);
+
+        Ok(())
+    }
+
+    async fn is_revoked(
+        &self,
+        jti: &str,
+        user_id: i32,
+        issued_at: OffsetDateTime,
+    ) -> Result<bool, AppError> {
+        self.sync_if_stale().await?;
+
+        let cache = self.cache.read().await;
+        if cache.tokens.contains_key(jti) {
+            return Ok(true);
+        }
+
+        Ok(cache
+            .users
+            .get(&user_id)
+            .is_some_and(|revoked_before| issued_at <= *revoked_before))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RevokedToken, UserTokenRevocation};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeRevocationRepository {
+        users: Mutex<Vec<UserTokenRevocation>>,
+    }
+
+    #[async_trait]
+    impl RevocationRepository for FakeRevocationRepository {
+        async fn revoke_token(
+            &self,
+            _jti: &str,
+            _user_id: i32,
+            _expires_at: OffsetDateTime,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        async fn revoke_user_tokens(
+            &self,
+            user_id: i32,
+            revoked_before: OffsetDateTime,
+        ) -> Result<(), AppError> {
+            self.users.lock().unwrap().push(UserTokenRevocation {
+                user_id,
+                revoked_before,
+            });
+            Ok(())
+        }
+
+        async fn get_active_revoked_tokens(&self) -> Result<Vec<RevokedToken>, AppError> {
+            Ok(Vec::new())
+        }
+
+        async fn get_user_revocations(&self) -> Result<Vec<UserTokenRevocation>, AppError> {
+            Ok(self.users.lock().unwrap().clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn revoke_all_covers_tokens_from_the_same_second() {
+        let service = RevocationServiceImpl::new(Arc::new(FakeRevocationRepository::default()));
+        let issued_at = OffsetDateTime::now_utc().replace_nanosecond(0).unwrap();
+
+        service.revoke_all_for_user(1).await.unwrap();
+
+        assert!(service.is_revoked("jti", 1, issued_at).await.unwrap());
+        assert!(!service
+            .is_revoked("jti", 1, issued_at + time::Duration::seconds(2))
+            .await
+            .unwrap());
+        assert!(!service.is_revoked("jti", 2, issued_at).await.unwrap());
+    }
+}
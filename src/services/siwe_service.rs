@@ -1,12 +1,23 @@
 use crate::error::AppError;
+use crate::repositories::SiweNonceRepository;
+use crate::services::{ContractWalletVerifier, RateLimitService};
 use async_trait::async_trait;
-use ethers::types::{Address, Signature};
-use siwe::{Message, VerificationOpts};
-use std::{str::FromStr, time::Duration};
-use time::OffsetDateTime;
+use ethers::types::Address;
+use siwe::{generate_nonce, Message};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// How long a client has to sign and submit a message after requesting a nonce.
+const NONCE_TTL: Duration = Duration::minutes(5);
+/// Nonces a single address may request per window, so the table cannot be flooded.
+const NONCES_PER_WINDOW: i32 = 20;
+const NONCE_WINDOW: Duration = Duration::minutes(5);
 
 #[async_trait]
 pub trait SiweService: Send + Sync {
+    async fn issue_nonce(&self, client_ip: IpAddr) -> Result<String, AppError>;
     async fn verify_signature(
         &self,
         message: String,
@@ -14,16 +25,76 @@ pub trait SiweService: Send + Sync {
     ) -> Result<Address, AppError>;
 }
 
-pub struct SiweServiceImpl;
+pub struct SiweServiceImpl {
+    nonce_repository: Arc<dyn SiweNonceRepository>,
+    contract_wallet_verifier: Arc<dyn ContractWalletVerifier>,
+    rate_limit_service: Arc<dyn RateLimitService>,
+    domain: String,
+    uri: String,
+}
 
 impl SiweServiceImpl {
-    pub fn new() -> Self {
-        Self
+    pub fn new(
+        nonce_repository: Arc<dyn SiweNonceRepository>,
+        contract_wallet_verifier: Arc<dyn ContractWalletVerifier>,
+        rate_limit_service: Arc<dyn RateLimitService>,
+        domain: String,
+        uri: String,
+    ) -> Self {
+        Self {
+            nonce_repository,
+            contract_wallet_verifier,
+            rate_limit_service,
+            domain,
+            uri,
+        }
+    }
+
+    /// Accepts either an EOA signature or one a contract wallet vouches for via EIP-1271.
+    async fn verify_message_signature(
+        &self,
+        message: &Message,
+        signature: &[u8],
+    ) -> Result<bool, AppError> {
+        if let Ok(signature) = <&[u8; 65]>::try_from(signature) {
+            if message.verify_eip191(signature).is_ok() {
+                return Ok(true);
+            }
+        }
+
+        let hash = message
+            .eip191_hash()
+            .map_err(|_| AppError::BadRequest("Invalid message".to_string()))?;
+        self.contract_wallet_verifier
+            .is_valid_signature(
+                message.chain_id,
+                Address::from(message.address),
+                hash,
+                signature,
+            )
+            .await
     }
 }
 
 #[async_trait]
 impl SiweService for SiweServiceImpl {
+    async fn issue_nonce(&self, client_ip: IpAddr) -> Result<String, AppError> {
+        self.rate_limit_service
+            .hit(
+                &format!("siwe_nonce:{}", client_ip),
+                NONCES_PER_WINDOW,
+                NONCE_WINDOW,
+            )
+            .await?;
+
+        let nonce = generate_nonce();
+        self.nonce_repository
+            .create_nonce(&nonce, OffsetDateTime::now_utc() + NONCE_TTL)
+            .await?;
+
+        Ok(nonce)
+    }
+
     async fn verify_signature(
         &self,
         message: String,
@@ -31,20 +102,26 @@ impl SiweService for SiweServiceImpl {
     ) -> Result<Address, AppError> {
         let message = Message::from_str(&message)
             .map_err(|_| AppError::BadRequest("Invalid message".to_string()))?;
-        let signature = Signature::from_str(&signature)
+        // Contract wallet signatures are not necessarily 65 bytes long.
+        let signature = hex::decode(signature.trim_start_matches("0x"))
             .map_err(|_| AppError::BadRequest("Invalid signature".to_string()))?;
 
-        message
-            .verify(
-                signature.to_vec().as_slice(),
-                &VerificationOpts {
-                    domain: None,
-                    nonce: None,
-                    timestamp: Some(OffsetDateTime::now_utc()),
-                },
-            )
-            .await
-            .map_err(|_| AppError::Unauthorized)?;
+        // A message signed for another site must not log anyone in here.
+        if message.domain != self.domain.as_str() || message.uri.as_str() != self.uri {
+            return Err(AppError::Unauthorized);
+        }
+
+        if !message.valid_at(&OffsetDateTime::now_utc())
+            || !self.verify_message_signature(&message, &signature).await?
+        {
+            return Err(AppError::Unauthorized);
+        }
+
+        // Consuming only after the signature checks out keeps a bad request
+        // from burning a nonce, and makes every nonce single-use.
+        if !self.nonce_repository.consume_nonce(&message.nonce).await? {
+            return Err(AppError::Unauthorized);
+        }
 
         Ok(ethers::types::H160(message.address))
     }
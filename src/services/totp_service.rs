@@ -0,0 +1,179 @@
+use crate::error::AppError;
+use crate::models::{TotpEnrollment, User, UserTotp};
+use crate::repositories::TotpRepository;
+use crate::utils::crypto::{constant_time_eq, random_token, sha256_hex};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+/// Codes from one step either side of the current one are accepted to allow for clock drift.
+const SKEW_STEPS: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[async_trait]
+pub trait TotpService: Send + Sync {
+    /// Generates a new secret for the user, to be confirmed with `confirm_enrollment`.
+    async fn begin_enrollment(&self, user: &User) -> Result<TotpEnrollment, AppError>;
+    /// Enables TOTP once the user proves their app works, returning the plaintext recovery codes.
+    async fn confirm_enrollment(&self, user_id: i32, code: &str) -> Result<Vec<String>, AppError>;
+    async fn is_enabled(&self, user_id: i32) -> Result<bool, AppError>;
+    /// Checks a TOTP code or an unused recovery code; either can only be used once.
+    async fn verify(&self, user_id: i32, code: &str) -> Result<bool, AppError>;
+    async fn disable(&self, user_id: i32, code: &str) -> Result<(), AppError>;
+}
+
+pub struct TotpServiceImpl {
+    totp_repository: Arc<dyn TotpRepository>,
+    issuer: String,
+}
+
+impl TotpServiceImpl {
+    pub fn new(totp_repository: Arc<dyn TotpRepository>, issuer: String) -> Self {
+        Self {
+            totp_repository,
+            issuer,
+        }
+    }
+
+    fn totp(&self, secret: Vec<u8>, account_name: &str) -> Result<TOTP, AppError> {
+        // The otpauth label uses ':' to separate issuer and account.
+        TOTP::new(
+            Algorithm::SHA1,
+            DIGITS,
+            SKEW_STEPS as u8,
+            STEP_SECONDS,
+            secret,
+            Some(self.issuer.replace(':', "")),
+            account_name.replace(':', ""),
+        )
+        .map_err(|_| AppError::InternalServerError)
+    }
+
+    fn stored_totp(&self, stored: &UserTotp) -> Result<TOTP, AppError> {
+        let secret = Secret::Encoded(stored.secret.clone())
+            .to_bytes()
+            .map_err(|_| AppError::InternalServerError)?;
+        self.totp(secret, &stored.user_id.to_string())
+    }
+
+    async fn confirmed_totp(&self, user_id: i32) -> Result<Option<UserTotp>, AppError> {
+        match self.totp_repository.get_totp(user_id).await {
+            Ok(stored) if stored.confirmed_at.is_some() => Ok(Some(stored)),
+            Ok(_) | Err(AppError::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Returns the time step `code` belongs to, if it is valid around the current time.
+fn matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs();
+    let current = now / STEP_SECONDS;
+
+    (current.saturating_sub(SKEW_STEPS)..=current + SKEW_STEPS)
+        .find(|step| constant_time_eq(&totp.generate(step * STEP_SECONDS), code))
+        .map(|step| step as i64)
+}
+
+/// Recovery codes are compared case-insensitively and without separators.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[async_trait]
+impl TotpService for TotpServiceImpl {
+    async fn begin_enrollment(&self, user: &User) -> Result<TotpEnrollment, AppError> {
+        let secret: [u8; 20] = rand::random();
+        let totp = self.totp(secret.to_vec(), &user.username)?;
+        let encoded_secret = totp.get_secret_base32();
+
+        if !self
+            .totp_repository
+            .save_pending_secret(user.id, &encoded_secret)
+            .await?
+        {
+            return Err(AppError::BadRequest(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        Ok(TotpEnrollment {
+            secret: encoded_secret,
+            otpauth_url: totp.get_url(),
+            qr_code: totp
+                .get_qr_base64()
+                .map_err(|_| AppError::InternalServerError)?,
+        })
+    }
+
+    async fn confirm_enrollment(&self, user_id: i32, code: &str) -> Result<Vec<String>, AppError> {
+        let stored = match self.totp_repository.get_totp(user_id).await {
+            Ok(stored) => stored,
+            Err(AppError::NotFound) => {
+                return Err(AppError::BadRequest(
+                    "Start two-factor enrollment first".to_string(),
+                ))
+            }
+            Err(e) => return Err(e),
+        };
+
+        let step = matching_step(&self.stored_totp(&stored)?, code.trim())
+            .ok_or_else(|| AppError::BadRequest("Invalid authentication code".to_string()))?;
+
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code = random_token(10).to_lowercase();
+                format!("{}-{}", &code[..5], &code[5..])
+            })
+            .collect();
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|code| sha256_hex(&normalize_recovery_code(code)))
+            .collect();
+
+        self.totp_repository.confirm(user_id, step, &hashes).await?;
+
+        Ok(codes)
+    }
+
+    async fn is_enabled(&self, user_id: i32) -> Result<bool, AppError> {
+        Ok(self.confirmed_totp(user_id).await?.is_some())
+    }
+
+    async fn verify(&self, user_id: i32, code: &str) -> Result<bool, AppError> {
+        let Some(stored) = self.confirmed_totp(user_id).await? else {
+            return Ok(false);
+        };
+        let code = code.trim();
+
+        if code.len() == DIGITS && code.chars().all(|c| c.is_ascii_digit()) {
+            return match matching_step(&self.stored_totp(&stored)?, code) {
+                Some(step) => self.totp_repository.record_step(user_id, step).await,
+                None => Ok(false),
+            };
+        }
+
+        self.totp_repository
+            .use_recovery_code(user_id, &sha256_hex(&normalize_recovery_code(code)))
+            .await
+    }
+
+    async fn disable(&self, user_id: i32, code: &str) -> Result<(), AppError> {
+        if !self.verify(user_id, code).await? {
+            return Err(AppError::BadRequest(
+                "Invalid authentication code".to_string(),
+            ));
+        }
+
+        self.totp_repository.delete_totp(user_id).await
+    }
+}
@@ -7,6 +7,7 @@ use std::sync::Arc;
 #[async_trait]
 pub trait UserService: Send + Sync {
     async fn get_all_users(&self) -> Result<Vec<User>, AppError>;
+    async fn get_user_by_id(&self, id: i32) -> Result<User, AppError>;
 }
 
 pub struct UserServiceImpl {
@@ -24,4 +25,8 @@ impl UserService for UserServiceImpl {
     async fn get_all_users(&self) -> Result<Vec<User>, AppError> {
         self.user_repository.get_all_users().await
     }
+
+    async fn get_user_by_id(&self, id: i32) -> Result<User, AppError> {
+        self.user_repository.get_user_by_id(id).await
+    }
 }
@@ -0,0 +1,597 @@
+use crate::error::AppError;
+use crate::models::{User, WebauthnChallenge};
+use crate::repositories::WebauthnCredentialRepository;
+use crate::services::RateLimitService;
+use crate::utils::crypto::random_token;
+use crate::utils::network::client_network;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, DiscoverableAuthentication, DiscoverableKey, PasskeyRegistration,
+    PublicKeyCredential, RegisterPublicKeyCredential, RequestChallengeResponse, Url,
+};
+use webauthn_rs::{Webauthn, WebauthnBuilder};
+use webauthn_rs_proto::ResidentKeyRequirement;
+
+/// How long a started ceremony can be finished, matching the browser-side timeout.
+const CEREMONY_TTL: Duration = Duration::from_secs(5 * 60);
+/// Ceremonies of each kind held in memory at once; past this the oldest are dropped.
+const MAX_PENDING_CEREMONIES: usize = 10_000;
+/// Logins a single network may start per window, so it cannot churn through the cap.
+const LOGINS_PER_WINDOW: i32 = 30;
+const LOGIN_WINDOW: time::Duration = time::Duration::minutes(5);
+
+#[async_trait]
+pub trait WebauthnService: Send + Sync {
+    async fn start_registration(
+        &self,
+        user: &User,
+    ) -> Result<WebauthnChallenge<CreationChallengeResponse>, AppError>;
+    async fn finish_registration(
+        &self,
+        user_id: i32,
+        ceremony_id: &str,
+        credential: &RegisterPublicKeyCredential,
+        name: Option<String>,
+    ) -> Result<(), AppError>;
+    /// Starts a usernameless login, letting the authenticator pick the account.
+    async fn start_login(
+        &self,
+        client_ip: IpAddr,
+    ) -> Result<WebauthnChallenge<RequestChallengeResponse>, AppError>;
+    /// Verifies the assertion and returns the ID of the user it belongs to.
+    async fn finish_login(
+        &self,
+        ceremony_id: &str,
+        credential: &PublicKeyCredential,
+    ) -> Result<i32, AppError>;
+}
+
+struct PendingCeremony<T> {
+    user_id: Option<i32>,
+    state: T,
+    created_at: Instant,
+}
+
+pub struct WebauthnServiceImpl {
+    webauthn: Webauthn,
+    credential_repository: Arc<dyn WebauthnCredentialRepository>,
+    rate_limit_service: Arc<dyn RateLimitService>,
+    registrations: Mutex<HashMap<String, PendingCeremony<(Uuid, PasskeyRegistration)>>>,
+    logins: Mutex<HashMap<String, PendingCeremony<DiscoverableAuthentication>>>,
+}
+
+impl WebauthnServiceImpl {
+    /// The relying party ID is the host of `app_base_url`, which must also be the page origin.
+    pub fn new(
+        credential_repository: Arc<dyn WebauthnCredentialRepository>,
+        rate_limit_service: Arc<dyn RateLimitService>,
+        app_base_url: &str,
+        rp_name: &str,
+    ) -> Result<Self, AppError> {
+        let origin = Url::parse(app_base_url).map_err(|_| AppError::InternalServerError)?;
+        let rp_id = origin.host_str().ok_or(AppError::InternalServerError)?;
+        let webauthn = WebauthnBuilder::new(rp_id, &origin)
+            .map_err(|_| AppError::InternalServerError)?
+            .rp_name(rp_name)
+            .build()
+            .map_err(|_| AppError::InternalServerError)?;
+
+        Ok(Self {
+            webauthn,
+            credential_repository,
+            rate_limit_service,
+            registrations: Mutex::new(HashMap::new()),
+            logins: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+fn insert_pending<T>(
+    pending: &Mutex<HashMap<String, PendingCeremony<T>>>,
+    user_id: Option<i32>,
+    state: T,
+) -> String {
+    let ceremony_id = random_token(32);
+    let mut pending = pending.lock().unwrap();
+    pending.retain(|_, p| p.created_at.elapsed() < CEREMONY_TTL);
+    if let Some(user_id) = user_id {
+        // A user only needs one ceremony at a time; a new one replaces the last.
+        pending.retain(|_, p| p.user_id != Some(user_id));
+    }
+    if pending.len() >= MAX_PENDING_CEREMONIES {
+        let oldest = pending
+            .iter()
+            .min_by_key(|(_, p)| p.created_at)
+            .map(|(id, _)| id.clone());
+        if let Some(oldest) = oldest {
+            pending.remove(&oldest);
+        }
+    }
+    pending.insert(
+        ceremony_id.clone(),
+        PendingCeremony {
+            user_id,
+            state,
+            created_at: Instant::now(),
+        },
+    );
+    ceremony_id
+}
+
+/// Removes the ceremony so it can only be finished once.
+fn take_pending<T>(
+    pending: &Mutex<HashMap<String, PendingCeremony<T>>>,
+    ceremony_id: &str,
+) -> Result<PendingCeremony<T>, AppError> {
+    let mut pending = pending.lock().unwrap();
+    pending.retain(|_, p| p.created_at.elapsed() < CEREMONY_TTL);
+    pending
+        .remove(ceremony_id)
+        .ok_or_else(|| AppError::BadRequest("Unknown or expired passkey ceremony".to_string()))
+}
+
+#[async_trait]
+impl WebauthnService for WebauthnServiceImpl {
+    async fn start_registration(
+        &self,
+        user: &User,
+    ) -> Result<WebauthnChallenge<CreationChallengeResponse>, AppError> {
+        let user_handle = self
+            .credential_repository
+            .get_user_handle(user.id)
+            .await?
+            .unwrap_or_else(Uuid::new_v4);
+        let existing = self
+            .credential_repository
+            .get_credentials_for_user(user.id)
+            .await?
+            .into_iter()
+            .map(|credential| credential.passkey.cred_id().clone())
+            .collect::<Vec<_>>();
+
+        let (mut options, registration) = self
+            .webauthn
+            .start_passkey_registration(user_handle, &user.username, &user.username, Some(existing))
+            .map_err(|_| AppError::InternalServerError)?;
+
+        // Usernameless login only works with credentials the authenticator can discover.
+        if let Some(selection) = options.public_key.authenticator_selection.as_mut() {
+            selection.require_resident_key = true;
+            selection.resident_key = Some(ResidentKeyRequirement::Required);
+        }
+
+        let ceremony_id = insert_pending(
+            &self.registrations,
+            Some(user.id),
+            (user_handle, registration),
+        );
+        Ok(WebauthnChallenge {
+            ceremony_id,
+            options,
+        })
+    }
+
+    async fn finish_registration(
+        &self,
+        user_id: i32,
+        ceremony_id: &str,
+        credential: &RegisterPublicKeyCredential,
+        name: Option<String>,
+    ) -> Result<(), AppError> {
+        let pending = take_pending(&self.registrations, ceremony_id)?;
+        if pending.user_id != Some(user_id) {
+            return Err(AppError::BadRequest(
+                "Unknown or expired passkey ceremony".to_string(),
+            ));
+        }
+        let (user_handle, registration) = pending.state;
+
+        let passkey = self
+            .webauthn
+            .finish_passkey_registration(credential, &registration)
+            .map_err(|_| AppError::BadRequest("Passkey registration failed".to_string()))?;
+
+        let name = name
+            .map(|name| name.trim().chars().take(100).collect::<String>())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "Passkey".to_string());
+        self.credential_repository
+            .create_credential(
+                user_id,
+                user_handle,
+                &hex::encode(passkey.cred_id()),
+                &passkey,
+                &name,
+            )
+            .await
+    }
+
+    async fn start_login(
+        &self,
+        client_ip: IpAddr,
+    ) -> Result<WebauthnChallenge<RequestChallengeResponse>, AppError> {
+        self.rate_limit_service
+            .hit(
+                &format!("webauthn_login:{}", client_network(client_ip)),
+                LOGINS_PER_WINDOW,
+                LOGIN_WINDOW,
+            )
+            .await?;
+
+        let (options, authentication) = self
+            .webauthn
+            .start_discoverable_authentication()
+            .map_err(|_| AppError::InternalServerError)?;
+
+        let ceremony_id = insert_pending(&self.logins, None, authentication);
+        Ok(WebauthnChallenge {
+            ceremony_id,
+            options,
+        })
+    }
+
+    async fn finish_login(
+        &self,
+        ceremony_id: &str,
+        credential: &PublicKeyCredential,
+    ) -> Result<i32, AppError> {
+        let pending = take_pending(&self.logins, ceremony_id)?;
+
+        let (user_handle, credential_id) = self
+            .webauthn
+            .identify_discoverable_authentication(credential)
+            .map_err(|_| AppError::Unauthorized)?;
+        let stored = match self
+            .credential_repository
+            .get_credential_by_id(&hex::encode(credential_id))
+            .await
+        {
+            Ok(stored) if stored.user_handle == user_handle => stored,
+            Ok(_) | Err(AppError::NotFound) => return Err(AppError::Unauthorized),
+            Err(e) => return Err(e),
+        };
+
+        let mut passkey = stored.passkey.0;
+        let result = self
+            .webauthn
+            .finish_discoverable_authentication(
+                credential,
+                pending.state,
+                &[DiscoverableKey::from(&passkey)],
+            )
+            .map_err(|_| AppError::Unauthorized)?;
+
+        // A counter that fails to advance suggests a cloned authenticator.
+        if result.counter() != 0 && i64::from(result.counter()) <= stored.sign_count {
+            return Err(AppError::Unauthorized);
+        }
+        passkey.update_credential(&result);
+        self.credential_repository
+            .record_use(stored.id, &passkey, i64::from(result.counter()))
+            .await?;
+
+        Ok(stored.user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WebauthnCredential;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use serde_json::{json, Value};
+    use sqlx::types::Json;
+    use std::net::Ipv4Addr;
+    use time::OffsetDateTime;
+    use webauthn_authenticator_rs::softpasskey::SoftPasskey;
+    use webauthn_authenticator_rs::WebauthnAuthenticator;
+    use webauthn_rs::prelude::Passkey;
+
+    const ORIGIN: &str = "https://auth.example.com";
+    const CLIENT_IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[derive(Default)]
+    struct FakeCredentialRepository {
+        credentials: Mutex<Vec<WebauthnCredential>>,
+    }
+
+    #[async_trait]
+    impl WebauthnCredentialRepository for FakeCredentialRepository {
+        async fn get_user_handle(&self, user_id: i32) -> Result<Option<Uuid>, AppError> {
+            let credentials = self.credentials.lock().unwrap();
+            Ok(credentials
+                .iter()
+                .find(|c| c.user_id == user_id)
+                .map(|c| c.user_handle))
+        }
+
+        async fn get_credentials_for_user(
+            &self,
+            user_id: i32,
+        ) -> Result<Vec<WebauthnCredential>, AppError> {
+            let credentials = self.credentials.lock().unwrap();
+            Ok(credentials
+                .iter()
+                .filter(|c| c.user_id == user_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn get_credential_by_id(
+            &self,
+            credential_id: &str,
+        ) -> Result<WebauthnCredential, AppError> {
+            let credentials = self.credentials.lock().unwrap();
+            credentials
+                .iter()
+                .find(|c| c.credential_id == credential_id)
+                .cloned()
+                .ok_or(AppError::NotFound)
+        }
+
+        async fn create_credential(
+            &self,
+            user_id: i32,
+            user_handle: Uuid,
+            credential_id: &str,
+            passkey: &Passkey,
+            name: &str,
+        ) -> Result<(), AppError> {
+            let mut credentials = self.credentials.lock().unwrap();
+            let id = credentials.len() as i32 + 1;
+            credentials.push(WebauthnCredential {
+                id,
+                user_id,
+                user_handle,
+                credential_id: credential_id.to_string(),
+                passkey: Json(passkey.clone()),
+                sign_count: 0,
+                name: name.to_string(),
+                created_at: OffsetDateTime::now_utc(),
+                last_used_at: None,
+            });
+            Ok(())
+        }
+
+        async fn record_use(
+            &self,
+            id: i32,
+            passkey: &Passkey,
+            sign_count: i64,
+        ) -> Result<(), AppError> {
+            let mut credentials = self.credentials.lock().unwrap();
+            let credential = credentials
+                .iter_mut()
+                .find(|c| c.id == id)
+                .ok_or(AppError::NotFound)?;
+            credential.passkey = Json(passkey.clone());
+            credential.sign_count = sign_count;
+            credential.last_used_at = Some(OffsetDateTime::now_utc());
+            Ok(())
+        }
+    }
+
+    struct NoRateLimit;
+
+    #[async_trait]
+    impl RateLimitService for NoRateLimit {
+        async fn hit(
+            &self,
+            _key: &str,
+            _limit: i32,
+            _window: time::Duration,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
+    struct Setup {
+        repository: Arc<FakeCredentialRepository>,
+        service: WebauthnServiceImpl,
+        authenticator: WebauthnAuthenticator<SoftPasskey>,
+        origin: Url,
+    }
+
+    fn setup() -> Setup {
+        let repository = Arc::new(FakeCredentialRepository::default());
+        let service =
+            WebauthnServiceImpl::new(repository.clone(), Arc::new(NoRateLimit), ORIGIN, "Test")
+                .unwrap();
+        Setup {
+            repository,
+            service,
+            authenticator: WebauthnAuthenticator::new(SoftPasskey::new(true)),
+            origin: Url::parse(ORIGIN).unwrap(),
+        }
+    }
+
+    fn user(id: i32) -> User {
+        User {
+            id,
+            username: format!("user{id}"),
+            email: None,
+            password_hash: None,
+            email_verified_at: None,
+        }
+    }
+
+    impl Setup {
+        /// Registers a passkey for `user` and returns its raw credential ID as sent by the browser.
+        async fn register(&mut self, user: &User) -> Value {
+            let challenge = self.service.start_registration(user).await.unwrap();
+            let credential = self.sign_registration(challenge.options);
+            self.service
+                .finish_registration(user.id, &challenge.ceremony_id, &credential, None)
+                .await
+                .unwrap();
+            serde_json::to_value(&credential).unwrap()["rawId"].clone()
+        }
+
+        /// Signs a registration challenge. The soft authenticator cannot create resident
+        /// keys, so the requirement is dropped before it sees the options.
+        fn sign_registration(
+            &mut self,
+            options: CreationChallengeResponse,
+        ) -> RegisterPublicKeyCredential {
+            let mut options = serde_json::to_value(&options).unwrap();
+            options["publicKey"]["authenticatorSelection"]["requireResidentKey"] = json!(false);
+            options["publicKey"]["authenticatorSelection"]["residentKey"] = json!("discouraged");
+            self.authenticator
+                .do_registration(
+                    self.origin.clone(),
+                    serde_json::from_value(options).unwrap(),
+                )
+                .unwrap()
+        }
+
+        /// Signs a login challenge. The soft authenticator has no discoverable credentials,
+        /// so the test supplies what a resident key would: which credential to use going
+        /// in, and the user handle coming out.
+        async fn sign_login(&mut self, raw_id: &Value) -> (String, PublicKeyCredential) {
+            let challenge = self.service.start_login(CLIENT_IP).await.unwrap();
+            let mut options = serde_json::to_value(&challenge.options).unwrap();
+            options["publicKey"]["allowCredentials"] =
+                json!([{ "type": "public-key", "id": raw_id }]);
+            let credential = self
+                .authenticator
+                .do_authentication(
+                    self.origin.clone(),
+                    serde_json::from_value(options).unwrap(),
+                )
+                .unwrap();
+
+            let user_handle = self.repository.credentials.lock().unwrap()[0].user_handle;
+            let mut credential = serde_json::to_value(&credential).unwrap();
+            credential["response"]["userHandle"] =
+                json!(URL_SAFE_NO_PAD.encode(user_handle.as_bytes()));
+            (
+                challenge.ceremony_id,
+                serde_json::from_value(credential).unwrap(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn registers_and_logs_in_with_passkey() {
+        let mut setup = setup();
+        let raw_id = setup.register(&user(1)).await;
+
+        let (ceremony_id, credential) = setup.sign_login(&raw_id).await;
+        let user_id = setup
+            .service
+            .finish_login(&ceremony_id, &credential)
+            .await
+            .unwrap();
+
+        assert_eq!(user_id, 1);
+        let stored = setup.repository.credentials.lock().unwrap()[0].clone();
+        assert!(stored.sign_count > 0);
+        assert!(stored.last_used_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn rejects_sign_counter_regression() {
+        let mut setup = setup();
+        let raw_id = setup.register(&user(1)).await;
+        // As if a clone of the authenticator had already been used further.
+        setup.repository.credentials.lock().unwrap()[0].sign_count = 1_000;
+
+        let (ceremony_id, credential) = setup.sign_login(&raw_id).await;
+        let result = setup.service.finish_login(&ceremony_id, &credential).await;
+
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+        assert_eq!(
+            setup.repository.credentials.lock().unwrap()[0].sign_count,
+            1_000
+        );
+    }
+
+    #[tokio::test]
+    async fn login_ceremony_cannot_be_reused() {
+        let mut setup = setup();
+        let raw_id = setup.register(&user(1)).await;
+        let (ceremony_id, credential) = setup.sign_login(&raw_id).await;
+        setup
+            .service
+            .finish_login(&ceremony_id, &credential)
+            .await
+            .unwrap();
+
+        let result = setup.service.finish_login(&ceremony_id, &credential).await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn registration_ceremony_cannot_be_reused() {
+        let mut setup = setup();
+        let alice = user(1);
+        let challenge = setup.service.start_registration(&alice).await.unwrap();
+        let credential = setup.sign_registration(challenge.options);
+        setup
+            .service
+            .finish_registration(1, &challenge.ceremony_id, &credential, None)
+            .await
+            .unwrap();
+
+        let result = setup
+            .service
+            .finish_registration(1, &challenge.ceremony_id, &credential, None)
+            .await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert_eq!(setup.repository.credentials.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn registration_ceremony_belongs_to_its_user() {
+        let mut setup = setup();
+        let challenge = setup.service.start_registration(&user(1)).await.unwrap();
+        let credential = setup.sign_registration(challenge.options);
+
+        let result = setup
+            .service
+            .finish_registration(2, &challenge.ceremony_id, &credential, None)
+            .await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert!(setup.repository.credentials.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn full_pending_logins_evict_the_oldest() {
+        let setup = setup();
+        let first = setup.service.start_login(CLIENT_IP).await.unwrap();
+        for _ in 1..MAX_PENDING_CEREMONIES {
+            setup.service.start_login(CLIENT_IP).await.unwrap();
+        }
+
+        let last = setup.service.start_login(CLIENT_IP).await.unwrap();
+
+        let logins = setup.service.logins.lock().unwrap();
+        assert_eq!(logins.len(), MAX_PENDING_CEREMONIES);
+        assert!(!logins.contains_key(&first.ceremony_id));
+        assert!(logins.contains_key(&last.ceremony_id));
+    }
+
+    #[tokio::test]
+    async fn new_registration_replaces_the_users_last() {
+        let setup = setup();
+        let first = setup.service.start_registration(&user(1)).await.unwrap();
+        setup.service.start_registration(&user(1)).await.unwrap();
+
+        assert_eq!(setup.service.registrations.lock().unwrap().len(), 1);
+        assert!(!setup
+            .service
+            .registrations
+            .lock()
+            .unwrap()
+            .contains_key(&first.ceremony_id));
+    }
+}
@@ -1,47 +1,216 @@
+use crate::middleware::PageContext;
+use crate::models::{
+    ApiKey, AuthorizeQuery, FieldError, NewApiKey, Product, ProductBundle, TotpEnrollment, User,
+};
 use askama::Template;
-use crate::models::{Product, ProductBundle, User};
 use std::collections::HashMap;
 #[derive(Template)]
 #[template(path = "users.html")]
 pub struct UsersTemplate {
+    pub ctx: PageContext,
     pub users: Vec<User>,
 }
 
+/// Validation errors to show next to the fields of a re-rendered form.
+#[derive(Default)]
+pub struct FieldErrors(pub Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn messages(&self, field: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|error| error.field == field)
+            .map(|error| error.message.as_str())
+            .collect()
+    }
+
+    pub fn has(&self, field: &str) -> bool {
+        self.0.iter().any(|error| error.field == field)
+    }
+}
+
+/// Values and errors to redisplay when a registration attempt is rejected.
+#[derive(Default)]
+pub struct RegisterForm {
+    pub username: String,
+    pub email: String,
+    pub errors: FieldErrors,
+}
+
 #[derive(Template)]
 #[template(path = "register.html")]
-pub struct RegisterTemplate {}
+pub struct RegisterTemplate {
+    pub ctx: PageContext,
+    pub form: RegisterForm,
+}
+
+#[derive(Template)]
+#[template(path = "register_form.html")]
+pub struct RegisterFormTemplate {
+    pub form: RegisterForm,
+}
+
+#[derive(Template)]
+#[template(path = "password_forgot.html")]
+pub struct ForgotPasswordTemplate {
+    pub ctx: PageContext,
+}
+
+pub struct ResetPasswordForm {
+    pub token: String,
+    pub errors: FieldErrors,
+}
+
+#[derive(Template)]
+#[template(path = "password_reset.html")]
+pub struct ResetPasswordTemplate {
+    pub ctx: PageContext,
+    pub form: ResetPasswordForm,
+}
+
+#[derive(Template)]
+#[template(path = "password_reset_form.html")]
+pub struct ResetPasswordFormTemplate {
+    pub form: ResetPasswordForm,
+}
+
+#[derive(Template)]
+#[template(path = "mfa_form.html")]
+pub struct MfaFormTemplate {
+    pub mfa_token: String,
+    pub error: Option<String>,
+    pub next: String,
+}
+
+#[derive(Template)]
+#[template(path = "two_factor.html")]
+pub struct TwoFactorTemplate {
+    pub ctx: PageContext,
+    pub enabled: bool,
+}
+
+#[derive(Template)]
+#[template(path = "totp_enroll.html")]
+pub struct TotpEnrollTemplate {
+    pub enrollment: TotpEnrollment,
+}
+
+#[derive(Template)]
+#[template(path = "recovery_codes.html")]
+pub struct RecoveryCodesTemplate {
+    pub codes: Vec<String>,
+}
+
+/// Values and errors to redisplay when creating an API key is rejected.
+#[derive(Default)]
+pub struct ApiKeyForm {
+    pub name: String,
+    pub scopes: String,
+    pub errors: FieldErrors,
+}
+
+#[derive(Template)]
+#[template(path = "api_keys.html")]
+pub struct ApiKeysTemplate {
+    pub ctx: PageContext,
+    pub keys: Vec<ApiKey>,
+    pub form: ApiKeyForm,
+}
+
+#[derive(Template)]
+#[template(path = "api_key_form.html")]
+pub struct ApiKeyFormTemplate {
+    pub form: ApiKeyForm,
+}
+
+#[derive(Template)]
+#[template(path = "api_key_created.html")]
+pub struct ApiKeyCreatedTemplate {
+    pub new_key: NewApiKey,
+}
+
+#[derive(Template)]
+#[template(path = "verify_email.html")]
+pub struct VerifyEmailTemplate {
+    pub ctx: PageContext,
+    pub verified: bool,
+}
+
+/// Asks the signed-in user whether an OAuth2 client may act on their behalf.
+#[derive(Template)]
+#[template(path = "oauth2_consent.html")]
+pub struct OAuth2ConsentTemplate {
+    pub ctx: PageContext,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    /// Echoed back in hidden fields so the decision can be checked again.
+    pub query: AuthorizeQuery,
+}
+
+#[derive(Template)]
+#[template(path = "device.html")]
+pub struct DeviceTemplate {
+    pub ctx: PageContext,
+    pub user_code: String,
+    pub error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "device_form.html")]
+pub struct DeviceFormTemplate {
+    pub user_code: String,
+    pub error: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "device_confirm.html")]
+pub struct DeviceConfirmTemplate {
+    pub client_name: String,
+    pub user_code: String,
+    pub scopes: Vec<String>,
+}
 
 #[derive(Template)]
 #[template(path = "login.html")]
-pub struct LoginTemplate {}
+pub struct LoginTemplate {
+    pub ctx: PageContext,
+    pub oauth_providers: Vec<String>,
+    /// Where to send the user once signed in.
+    pub next: String,
+}
 
 #[derive(Template)]
 #[template(path = "products/list.html")]
 pub struct ProductListTemplate {
+    pub ctx: PageContext,
     pub products: Vec<Product>,
 }
 
 #[derive(Template)]
 #[template(path = "products/detail.html")]
 pub struct ProductDetailTemplate {
+    pub ctx: PageContext,
     pub product: Product,
 }
 
 #[derive(Template)]
 #[template(path = "products/form.html")]
 pub struct ProductFormTemplate {
+    pub ctx: PageContext,
     pub product: Option<Product>,
     pub action: String,
 }
 #[derive(Template)]
 #[template(path = "bundles/list.html")]
 pub struct BundleListTemplate {
+    pub ctx: PageContext,
     pub bundles: Vec<ProductBundle>,
 }
 
 #[derive(Template)]
 #[template(path = "bundles/detail.html")]
 pub struct BundleDetailTemplate {
+    pub ctx: PageContext,
     pub bundle: ProductBundle,
     pub products: Vec<(Product, i32)>,
 }
@@ -49,8 +218,9 @@ pub struct BundleDetailTemplate {
 #[derive(Template)]
 #[template(path = "bundles/form.html")]
 pub struct BundleFormTemplate {
+    pub ctx: PageContext,
     pub bundle: Option<ProductBundle>,
     pub all_products: Vec<Product>,
     pub selected_products: HashMap<i32, i32>,
     pub action: String,
-}
\ No newline at end of file
+}
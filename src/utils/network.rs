@@ -0,0 +1,13 @@
+use std::net::{IpAddr, Ipv6Addr};
+
+/// The network a client address belongs to, for rate limiting. IPv6 clients are
+/// usually handed a whole /64, so limiting by single address would be no limit at all.
+pub fn client_network(ip: IpAddr) -> String {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => {
+            let prefix = u128::from(ip) & !(u64::MAX as u128);
+            format!("{}/64", Ipv6Addr::from(prefix))
+        }
+    }
+}
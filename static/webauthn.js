@@ -0,0 +1,89 @@
+// Converts between the base64url strings used by the server and the
+// ArrayBuffers expected by navigator.credentials.
+function base64urlToBuffer(value) {
+    const base64 = value.replace(/-/g, "+").replace(/_/g, "/");
+    const padded = base64 + "=".repeat((4 - (base64.length % 4)) % 4);
+    return Uint8Array.from(atob(padded), (c) => c.charCodeAt(0)).buffer;
+}
+
+function bufferToBase64url(buffer) {
+    const bytes = String.fromCharCode(...new Uint8Array(buffer));
+    return btoa(bytes).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
+async function postJson(url, csrfToken, body) {
+    const response = await fetch(url, {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+            "X-CSRF-Token": csrfToken,
+        },
+        body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    if (!response.ok) {
+        throw new Error(`${url} failed with status ${response.status}`);
+    }
+    return response;
+}
+
+async function registerPasskey(csrfToken, name) {
+    const { ceremony_id, options } = await (
+        await postJson("/webauthn/register/start", csrfToken)
+    ).json();
+    const publicKey = options.publicKey;
+    publicKey.challenge = base64urlToBuffer(publicKey.challenge);
+    publicKey.user.id = base64urlToBuffer(publicKey.user.id);
+    (publicKey.excludeCredentials || []).forEach((credential) => {
+        credential.id = base64urlToBuffer(credential.id);
+    });
+
+    const credential = await navigator.credentials.create({ publicKey });
+    await postJson("/webauthn/register/finish", csrfToken, {
+        ceremony_id,
+        name,
+        credential: {
+            id: credential.id,
+            rawId: bufferToBase64url(credential.rawId),
+            type: credential.type,
+            extensions: credential.getClientExtensionResults(),
+            response: {
+                attestationObject: bufferToBase64url(credential.response.attestationObject),
+                clientDataJSON: bufferToBase64url(credential.response.clientDataJSON),
+                transports: credential.response.getTransports
+                    ? credential.response.getTransports()
+                    : [],
+            },
+        },
+    });
+}
+
+async function loginWithPasskey(csrfToken) {
+    const { ceremony_id, options } = await (
+        await postJson("/webauthn/login/start", csrfToken)
+    ).json();
+    const publicKey = options.publicKey;
+    publicKey.challenge = base64urlToBuffer(publicKey.challenge);
+    (publicKey.allowCredentials || []).forEach((credential) => {
+        credential.id = base64urlToBuffer(credential.id);
+    });
+
+    const credential = await navigator.credentials.get({ publicKey });
+    const { response } = credential;
+    await postJson("/webauthn/login/finish", csrfToken, {
+        ceremony_id,
+        credential: {
+            id: credential.id,
+            rawId: bufferToBase64url(credential.rawId),
+            type: credential.type,
+            extensions: credential.getClientExtensionResults(),
+            response: {
+                authenticatorData: bufferToBase64url(response.authenticatorData),
+                clientDataJSON: bufferToBase64url(response.clientDataJSON),
+                signature: bufferToBase64url(response.signature),
+                userHandle: response.userHandle
+                    ? bufferToBase64url(response.userHandle)
+                    : null,
+            },
+        },
+    });
+}